@@ -0,0 +1,33 @@
+CREATE TYPE category AS ENUM ('CUP', 'ST', 'CHL', 'TCX', 'GT3', 'GT4', 'GT2');
+
+CREATE TABLE driver
+(
+    id   BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE track
+(
+    id   BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE car
+(
+    id       BIGSERIAL PRIMARY KEY,
+    name     TEXT     NOT NULL UNIQUE,
+    category category NOT NULL
+);
+
+CREATE TABLE best_lap
+(
+    id          BIGSERIAL PRIMARY KEY,
+    driver_id   BIGINT      NOT NULL REFERENCES driver (id),
+    track_id    BIGINT      NOT NULL REFERENCES track (id),
+    car_id      BIGINT      NOT NULL REFERENCES car (id),
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    lap_time_ms BIGINT      NOT NULL,
+    UNIQUE (driver_id, track_id, car_id)
+);
+
+CREATE INDEX best_lap_track_car_idx ON best_lap (track_id, car_id, lap_time_ms);
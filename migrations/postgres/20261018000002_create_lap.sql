@@ -0,0 +1,14 @@
+CREATE TABLE lap
+(
+    id            BIGSERIAL PRIMARY KEY,
+    driver_id     BIGINT      NOT NULL REFERENCES driver (id),
+    track_id      BIGINT      NOT NULL REFERENCES track (id),
+    car_id        BIGINT      NOT NULL REFERENCES car (id),
+    session_index INTEGER     NOT NULL,
+    lap_number    INTEGER     NOT NULL,
+    lap_time_ms   BIGINT      NOT NULL,
+    is_valid      BOOLEAN     NOT NULL,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX lap_driver_track_car_idx ON lap (driver_id, track_id, car_id, created_at);
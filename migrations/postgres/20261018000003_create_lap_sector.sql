@@ -0,0 +1,10 @@
+CREATE TABLE lap_sector
+(
+    lap_id         BIGINT  NOT NULL REFERENCES lap (id) ON DELETE CASCADE,
+    sector_index   INTEGER NOT NULL,
+    sector_time_ms BIGINT  NOT NULL,
+    PRIMARY KEY (lap_id, sector_index)
+);
+
+ALTER TABLE best_lap
+    ADD COLUMN lap_id BIGINT REFERENCES lap (id);
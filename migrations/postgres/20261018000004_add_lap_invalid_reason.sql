@@ -0,0 +1,2 @@
+ALTER TABLE lap
+    ADD COLUMN invalid_reason TEXT;
@@ -0,0 +1,43 @@
+CREATE TYPE session_type AS ENUM ('PRACTICE', 'QUALIFYING', 'RACE', 'HOTLAP', 'UNKNOWN');
+CREATE TYPE rain_level AS ENUM ('NO_RAIN', 'DRIZZLE', 'LIGHT_RAIN', 'MEDIUM_RAIN', 'HEAVY_RAIN', 'THUNDERSTORM');
+CREATE TYPE grip_status AS ENUM ('GREEN', 'FAST', 'OPTIMUM', 'GREASY', 'DAMP', 'WET', 'FLOODED');
+
+CREATE TABLE session
+(
+    id                BIGSERIAL PRIMARY KEY,
+    driver_id         BIGINT       NOT NULL REFERENCES driver (id),
+    track_id          BIGINT       NOT NULL REFERENCES track (id),
+    car_id            BIGINT       NOT NULL REFERENCES car (id),
+    session_index     INTEGER      NOT NULL,
+    session_type      session_type NOT NULL DEFAULT 'UNKNOWN',
+    air_temperature   REAL,
+    track_temperature REAL,
+    rain_level        rain_level,
+    grip_status       grip_status,
+    started_at        TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    ended_at          TIMESTAMPTZ
+);
+
+-- Laps recorded before sessions were tracked get one session per sim session and day, without conditions.
+INSERT INTO session (driver_id, track_id, car_id, session_index, started_at, ended_at)
+SELECT driver_id, track_id, car_id, session_index, MIN(created_at), MAX(created_at)
+FROM lap
+GROUP BY driver_id, track_id, car_id, session_index, created_at::date;
+
+ALTER TABLE lap
+    ADD COLUMN session_id BIGINT REFERENCES session (id);
+
+UPDATE lap
+SET session_id = s.id
+FROM session s
+WHERE s.driver_id = lap.driver_id
+  AND s.track_id = lap.track_id
+  AND s.car_id = lap.car_id
+  AND s.session_index = lap.session_index
+  AND lap.created_at BETWEEN s.started_at AND s.ended_at;
+
+ALTER TABLE lap
+    ALTER COLUMN session_id SET NOT NULL,
+    DROP COLUMN session_index;
+
+CREATE INDEX lap_session_idx ON lap (session_id);
@@ -0,0 +1,56 @@
+CREATE TYPE surface AS ENUM ('DRY', 'WET');
+
+ALTER TABLE lap
+    ADD COLUMN surface           surface NOT NULL DEFAULT 'DRY',
+    ADD COLUMN track_temperature REAL;
+
+-- Laps take the conditions their session started in. Laps without recorded conditions stay dry.
+UPDATE lap
+SET surface           = CASE
+                            WHEN s.rain_level <> 'NO_RAIN' OR s.grip_status IN ('DAMP', 'WET', 'FLOODED')
+                                THEN 'WET'::surface
+                            ELSE 'DRY'::surface END,
+    track_temperature = s.track_temperature
+FROM session s
+WHERE s.id = lap.session_id;
+
+-- Best laps recorded before laps were kept get a session and lap of their own so they still show up
+-- on leaderboards, which are now built from laps.
+DO
+$$
+    DECLARE
+        best       RECORD;
+        session_id BIGINT;
+        new_lap_id BIGINT;
+    BEGIN
+        FOR best IN SELECT * FROM best_lap WHERE lap_id IS NULL
+            LOOP
+                INSERT INTO session (driver_id, track_id, car_id, session_index, started_at, ended_at)
+                VALUES (best.driver_id, best.track_id, best.car_id, 0, best.created_at, best.created_at)
+                RETURNING id INTO session_id;
+
+                INSERT INTO lap (driver_id, track_id, car_id, session_id, lap_number, lap_time_ms, is_valid,
+                                 created_at)
+                VALUES (best.driver_id, best.track_id, best.car_id, session_id, 0, best.lap_time_ms, TRUE,
+                        best.created_at)
+                RETURNING id INTO new_lap_id;
+
+                UPDATE best_lap SET lap_id = new_lap_id WHERE id = best.id;
+            END LOOP;
+    END
+$$;
+
+ALTER TABLE best_lap
+    ADD COLUMN surface surface NOT NULL DEFAULT 'DRY',
+    ALTER COLUMN lap_id SET NOT NULL;
+
+UPDATE best_lap
+SET surface = l.surface
+FROM lap l
+WHERE l.id = best_lap.lap_id;
+
+ALTER TABLE best_lap
+    DROP CONSTRAINT best_lap_driver_id_track_id_car_id_key,
+    ADD CONSTRAINT best_lap_driver_id_track_id_car_id_surface_key UNIQUE (driver_id, track_id, car_id, surface);
+
+CREATE INDEX lap_track_surface_idx ON lap (track_id, surface) WHERE is_valid;
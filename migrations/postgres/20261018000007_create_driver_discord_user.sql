@@ -0,0 +1,6 @@
+-- Optional Discord accounts to mention when a driver's record is beaten.
+CREATE TABLE driver_discord_user
+(
+    driver_id       BIGINT PRIMARY KEY REFERENCES driver (id),
+    discord_user_id TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Cars the tracker doesn't recognise yet, until an admin gives them a category.
+ALTER TYPE category ADD VALUE IF NOT EXISTS 'UNKNOWN';
+
+-- Names admins give to tracks and cars the tracker doesn't recognise.
+ALTER TABLE track ADD COLUMN display_name TEXT;
+ALTER TABLE car ADD COLUMN display_name TEXT;
@@ -0,0 +1,9 @@
+-- What the catalogue knows about each track. Laps are kept apart from those set on other layouts
+-- of a track, and the layout is empty when it isn't known.
+ALTER TABLE track ADD COLUMN layout TEXT NOT NULL DEFAULT '';
+ALTER TABLE track ADD COLUMN length_km DOUBLE PRECISION;
+ALTER TABLE track ADD COLUMN sector_count INTEGER;
+ALTER TABLE track ADD COLUMN country TEXT;
+
+ALTER TABLE track DROP CONSTRAINT track_name_key;
+ALTER TABLE track ADD UNIQUE (name, layout);
@@ -0,0 +1,19 @@
+-- The game version each lap was set on and the balance of performance era that puts it in. Laps
+-- set before eras were recorded could be from any of them, so they're put in a legacy era of their
+-- own. Every lap stored from now on has its era set.
+ALTER TABLE lap
+    ADD COLUMN game_version TEXT,
+    ADD COLUMN bop_era      TEXT NOT NULL DEFAULT 'legacy';
+
+ALTER TABLE lap
+    ALTER COLUMN bop_era DROP DEFAULT;
+
+ALTER TABLE best_lap
+    ADD COLUMN bop_era TEXT NOT NULL DEFAULT 'legacy',
+    DROP CONSTRAINT best_lap_driver_id_track_id_car_id_surface_key,
+    ADD CONSTRAINT best_lap_driver_id_track_id_car_id_surface_bop_era_key UNIQUE (driver_id, track_id, car_id, surface, bop_era);
+
+ALTER TABLE best_lap
+    ALTER COLUMN bop_era DROP DEFAULT;
+
+CREATE INDEX lap_track_bop_era_idx ON lap (track_id, bop_era) WHERE is_valid;
@@ -0,0 +1,14 @@
+-- Writes waiting for the database to be reachable again, oldest first. Ids are never reused, as
+-- they double as placeholders for the ids of the rows the writes will create.
+CREATE TABLE queued_write
+(
+    id      INTEGER PRIMARY KEY AUTOINCREMENT,
+    payload TEXT NOT NULL
+);
+
+-- The real ids of replayed writes, for queued writes that still refer to their placeholder.
+CREATE TABLE replayed_id
+(
+    placeholder INTEGER PRIMARY KEY,
+    id          INTEGER NOT NULL
+);
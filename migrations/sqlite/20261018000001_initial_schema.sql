@@ -0,0 +1,85 @@
+-- The same schema as the Postgres migrations, with enums stored as text.
+CREATE TABLE driver
+(
+    id   INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE track
+(
+    id   INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE car
+(
+    id       INTEGER PRIMARY KEY,
+    name     TEXT NOT NULL UNIQUE,
+    category TEXT NOT NULL CHECK (category IN ('CUP', 'ST', 'CHL', 'TCX', 'GT3', 'GT4', 'GT2'))
+);
+
+CREATE TABLE session
+(
+    id                INTEGER PRIMARY KEY,
+    driver_id         INTEGER NOT NULL REFERENCES driver (id),
+    track_id          INTEGER NOT NULL REFERENCES track (id),
+    car_id            INTEGER NOT NULL REFERENCES car (id),
+    session_index     INTEGER NOT NULL,
+    session_type      TEXT    NOT NULL DEFAULT 'UNKNOWN',
+    air_temperature   REAL,
+    track_temperature REAL,
+    rain_level        TEXT,
+    grip_status       TEXT,
+    started_at        TEXT    NOT NULL,
+    ended_at          TEXT
+);
+
+CREATE TABLE lap
+(
+    id                INTEGER PRIMARY KEY,
+    driver_id         INTEGER NOT NULL REFERENCES driver (id),
+    track_id          INTEGER NOT NULL REFERENCES track (id),
+    car_id            INTEGER NOT NULL REFERENCES car (id),
+    session_id        INTEGER NOT NULL REFERENCES session (id),
+    lap_number        INTEGER NOT NULL,
+    lap_time_ms       INTEGER NOT NULL,
+    is_valid          BOOLEAN NOT NULL,
+    invalid_reason    TEXT,
+    surface           TEXT    NOT NULL DEFAULT 'DRY' CHECK (surface IN ('DRY', 'WET')),
+    track_temperature REAL,
+    created_at        TEXT    NOT NULL
+);
+
+CREATE INDEX lap_driver_track_car_idx ON lap (driver_id, track_id, car_id, created_at);
+CREATE INDEX lap_session_idx ON lap (session_id);
+CREATE INDEX lap_track_surface_idx ON lap (track_id, surface) WHERE is_valid;
+
+CREATE TABLE lap_sector
+(
+    lap_id         INTEGER NOT NULL REFERENCES lap (id) ON DELETE CASCADE,
+    sector_index   INTEGER NOT NULL,
+    sector_time_ms INTEGER NOT NULL,
+    PRIMARY KEY (lap_id, sector_index)
+);
+
+CREATE TABLE best_lap
+(
+    id          INTEGER PRIMARY KEY,
+    driver_id   INTEGER NOT NULL REFERENCES driver (id),
+    track_id    INTEGER NOT NULL REFERENCES track (id),
+    car_id      INTEGER NOT NULL REFERENCES car (id),
+    created_at  TEXT    NOT NULL,
+    lap_time_ms INTEGER NOT NULL,
+    lap_id      INTEGER NOT NULL REFERENCES lap (id),
+    surface     TEXT    NOT NULL DEFAULT 'DRY',
+    UNIQUE (driver_id, track_id, car_id, surface)
+);
+
+CREATE INDEX best_lap_track_car_idx ON best_lap (track_id, car_id, lap_time_ms);
+
+-- Optional Discord accounts to mention when a driver's record is beaten.
+CREATE TABLE driver_discord_user
+(
+    driver_id       INTEGER PRIMARY KEY REFERENCES driver (id),
+    discord_user_id TEXT NOT NULL
+);
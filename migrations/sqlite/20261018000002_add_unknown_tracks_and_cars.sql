@@ -0,0 +1,20 @@
+-- Names admins give to tracks and cars the tracker doesn't recognise.
+ALTER TABLE track ADD COLUMN display_name TEXT;
+
+-- SQLite can't change a CHECK constraint, so the car table is rebuilt to allow cars the tracker
+-- doesn't recognise yet. Migrations run with foreign keys off for this.
+CREATE TABLE car_new
+(
+    id           INTEGER PRIMARY KEY,
+    name         TEXT NOT NULL UNIQUE,
+    category     TEXT NOT NULL CHECK (category IN ('CUP', 'ST', 'CHL', 'TCX', 'GT3', 'GT4', 'GT2', 'UNKNOWN')),
+    display_name TEXT
+);
+
+INSERT INTO car_new (id, name, category)
+SELECT id, name, category
+FROM car;
+
+DROP TABLE car;
+
+ALTER TABLE car_new RENAME TO car;
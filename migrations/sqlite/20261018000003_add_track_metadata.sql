@@ -0,0 +1,22 @@
+-- What the catalogue knows about each track. Laps are kept apart from those set on other layouts
+-- of a track, and the layout is empty when it isn't known. The table is rebuilt as SQLite can't
+-- drop the unique constraint on the name.
+CREATE TABLE track_new
+(
+    id           INTEGER PRIMARY KEY,
+    name         TEXT NOT NULL,
+    display_name TEXT,
+    layout       TEXT NOT NULL DEFAULT '',
+    length_km    REAL,
+    sector_count INTEGER,
+    country      TEXT,
+    UNIQUE (name, layout)
+);
+
+INSERT INTO track_new (id, name, display_name)
+SELECT id, name, display_name
+FROM track;
+
+DROP TABLE track;
+
+ALTER TABLE track_new RENAME TO track;
@@ -0,0 +1,31 @@
+-- The game version each lap was set on and the balance of performance era that puts it in. Laps
+-- set before eras were recorded could be from any of them, so they're put in a legacy era of their
+-- own.
+ALTER TABLE lap ADD COLUMN game_version TEXT;
+ALTER TABLE lap ADD COLUMN bop_era TEXT NOT NULL DEFAULT 'legacy';
+
+-- Best laps are kept per era. The table is rebuilt as SQLite can't change its unique constraint.
+CREATE TABLE best_lap_new
+(
+    id          INTEGER PRIMARY KEY,
+    driver_id   INTEGER NOT NULL REFERENCES driver (id),
+    track_id    INTEGER NOT NULL REFERENCES track (id),
+    car_id      INTEGER NOT NULL REFERENCES car (id),
+    created_at  TEXT    NOT NULL,
+    lap_time_ms INTEGER NOT NULL,
+    lap_id      INTEGER NOT NULL REFERENCES lap (id),
+    surface     TEXT    NOT NULL DEFAULT 'DRY',
+    bop_era     TEXT    NOT NULL,
+    UNIQUE (driver_id, track_id, car_id, surface, bop_era)
+);
+
+INSERT INTO best_lap_new (id, driver_id, track_id, car_id, created_at, lap_time_ms, lap_id, surface, bop_era)
+SELECT id, driver_id, track_id, car_id, created_at, lap_time_ms, lap_id, surface, 'legacy'
+FROM best_lap;
+
+DROP TABLE best_lap;
+
+ALTER TABLE best_lap_new RENAME TO best_lap;
+
+CREATE INDEX best_lap_track_car_idx ON best_lap (track_id, car_id, lap_time_ms);
+CREATE INDEX lap_track_bop_era_idx ON lap (track_id, bop_era) WHERE is_valid;
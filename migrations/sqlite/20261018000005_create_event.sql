@@ -0,0 +1,8 @@
+-- SQLite can't notify other processes, so published events are kept here briefly for `serve`
+-- to poll.
+CREATE TABLE event
+(
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    payload    TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
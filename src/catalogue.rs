@@ -0,0 +1,491 @@
+use crate::cars::CarCategory;
+use crate::CarRow;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+/// The catalogue bundled with the tracker.
+const BUNDLED: &str = include_str!("../catalogue.json");
+
+/// The era of laps set before eras were recorded. They could be from any balance of performance,
+/// so they're only shown under all time or when this era is asked for.
+pub(crate) const LEGACY_BOP_ERA: &str = "legacy";
+
+/// The era of laps and best laps queued before eras were recorded.
+pub(crate) fn legacy_bop_era() -> String {
+    LEGACY_BOP_ERA.to_string()
+}
+
+/// The tracks and cars the tracker recognises, keyed by the game's names for them, ignoring case.
+pub(crate) struct Catalogue {
+    tracks: HashMap<String, TrackEntry>,
+    cars: HashMap<String, CarEntry>,
+    /// Oldest first.
+    bop_eras: Vec<BopEra>,
+}
+
+#[derive(Deserialize)]
+struct CatalogueFile {
+    #[serde(default)]
+    tracks: Vec<TrackEntry>,
+    #[serde(default)]
+    cars: Vec<CarEntry>,
+    #[serde(default)]
+    bop_eras: Vec<BopEra>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct TrackEntry {
+    /// The game's name for the track.
+    pub(crate) id: String,
+    pub(crate) display_name: String,
+    /// Which revision of the track the game has, e.g. the season it was last changed for. Laps set
+    /// on other layouts are ranked separately.
+    pub(crate) layout: String,
+    pub(crate) length_km: f64,
+    pub(crate) sector_count: i32,
+    pub(crate) country: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct CarEntry {
+    /// The game's name for the car.
+    pub(crate) id: String,
+    pub(crate) display_name: String,
+    pub(crate) category: CarCategory,
+    pub(crate) manufacturer: String,
+    /// The model the car is a version of, e.g. the same for a car and its Evo, so they can be
+    /// ranked together.
+    pub(crate) family: String,
+    /// The model year.
+    pub(crate) year: i32,
+    /// The season whose balance of performance the car was added with.
+    pub(crate) bop_era: String,
+}
+
+/// A stretch of game versions sharing a balance of performance. Laps set in different eras are
+/// ranked separately unless looking at all time.
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct BopEra {
+    pub(crate) id: String,
+    /// The first version of the game in the era.
+    game_version: String,
+}
+
+/// The cars a leaderboard ranks laps in.
+pub(crate) struct CarFilter {
+    /// `None` ranks every car.
+    pub(crate) car_ids: Option<Vec<i64>>,
+    pub(crate) category: Option<String>,
+}
+
+/// Why the cars a leaderboard was asked for couldn't be picked.
+#[derive(Debug, PartialEq)]
+pub(crate) enum CarFilterError {
+    /// A car, manufacturer or family nothing is known about.
+    Unknown(String),
+    /// A category that doesn't exist.
+    Invalid(String),
+}
+
+impl Display for CarFilterError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CarFilterError::Unknown(message) | CarFilterError::Invalid(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl Catalogue {
+    /// Loads the bundled catalogue, with the entries of the file at `overrides` added to it or
+    /// replacing the bundled ones with the same id.
+    pub(crate) fn load(overrides: Option<&Path>) -> Result<Catalogue> {
+        let mut catalogue = Catalogue {
+            tracks: HashMap::new(),
+            cars: HashMap::new(),
+            bop_eras: Vec::new(),
+        };
+        catalogue.add(serde_json::from_str(BUNDLED).context("Invalid bundled catalogue")?);
+        if let Some(path) = overrides {
+            let file = std::fs::read_to_string(path)
+                .with_context(|| format!("Couldn't read the catalogue {}", path.display()))?;
+            catalogue.add(
+                serde_json::from_str(&file)
+                    .with_context(|| format!("Invalid catalogue {}", path.display()))?,
+            );
+        }
+        Ok(catalogue)
+    }
+
+    fn add(&mut self, file: CatalogueFile) {
+        for track in file.tracks {
+            self.tracks.insert(track.id.to_lowercase(), track);
+        }
+        for car in file.cars {
+            self.cars.insert(car.id.to_lowercase(), car);
+        }
+        for era in file.bop_eras {
+            self.bop_eras.retain(|existing| existing.id != era.id);
+            self.bop_eras.push(era);
+        }
+        self.bop_eras
+            .sort_by_key(|era| version_parts(&era.game_version));
+    }
+
+    pub(crate) fn track(&self, id: &str) -> Option<&TrackEntry> {
+        self.tracks.get(&id.to_lowercase())
+    }
+
+    pub(crate) fn car(&self, id: &str) -> Option<&CarEntry> {
+        self.cars.get(&id.to_lowercase())
+    }
+
+    /// What to show for a track: the name an admin gave it, the catalogue's name for it, or
+    /// failing those the game's. Layouts other than the catalogue's are named too.
+    pub(crate) fn track_label(&self, id: &str, display_name: Option<&str>, layout: &str) -> String {
+        let track = self.track(id);
+        let label = display_name
+            .or(track.map(|track| track.display_name.as_str()))
+            .unwrap_or(id);
+        match track {
+            Some(track) if track.layout != layout => {
+                let layout = if layout.is_empty() { "earlier" } else { layout };
+                format!("{label} ({layout} layout)")
+            }
+            _ => label.to_string(),
+        }
+    }
+
+    /// Whether a car is made by `manufacturer` and belongs to `family`, for whichever are given,
+    /// ignoring case. Cars the catalogue doesn't have are only in the group of all cars.
+    pub(crate) fn in_group(
+        &self,
+        id: &str,
+        manufacturer: Option<&str>,
+        family: Option<&str>,
+    ) -> bool {
+        if manufacturer.is_none() && family.is_none() {
+            return true;
+        }
+        self.car(id).is_some_and(|car| {
+            manufacturer
+                .is_none_or(|manufacturer| car.manufacturer.eq_ignore_ascii_case(manufacturer))
+                && family.is_none_or(|family| car.family.eq_ignore_ascii_case(family))
+        })
+    }
+
+    /// Says which of `manufacturer` and `family` the catalogue has no cars for, if either.
+    pub(crate) fn unknown_group(
+        &self,
+        manufacturer: Option<&str>,
+        family: Option<&str>,
+    ) -> Option<String> {
+        if let Some(manufacturer) = manufacturer {
+            if !self
+                .cars
+                .values()
+                .any(|car| car.manufacturer.eq_ignore_ascii_case(manufacturer))
+            {
+                return Some(format!("Unknown manufacturer {manufacturer}"));
+            }
+        }
+        if let Some(family) = family {
+            if !self
+                .cars
+                .values()
+                .any(|car| car.family.eq_ignore_ascii_case(family))
+            {
+                return Some(format!("Unknown car family {family}"));
+            }
+        }
+        None
+    }
+
+    /// Picks the cars, out of those laps have been recorded in, that a leaderboard asked for a
+    /// `car`, or a `manufacturer` and `family`, ranks. A car asked for is ranked on its own,
+    /// whatever group is given with it.
+    pub(crate) fn car_filter(
+        &self,
+        cars: &[CarRow],
+        car: Option<&str>,
+        category: Option<&str>,
+        manufacturer: Option<&str>,
+        family: Option<&str>,
+    ) -> Result<CarFilter, CarFilterError> {
+        let category = match category {
+            Some(category) => Some(
+                category
+                    .to_uppercase()
+                    .parse::<CarCategory>()
+                    .map_err(|_| CarFilterError::Invalid(format!("Unknown category {category}")))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let car_ids = match car {
+            Some(car) => {
+                let row = cars.iter().find(|row| row.name == car).ok_or_else(|| {
+                    CarFilterError::Unknown(format!("No laps have been recorded in {car}"))
+                })?;
+                Some(vec![row.id])
+            }
+            None if manufacturer.is_some() || family.is_some() => {
+                if let Some(error) = self.unknown_group(manufacturer, family) {
+                    return Err(CarFilterError::Unknown(error));
+                }
+                Some(
+                    cars.iter()
+                        .filter(|row| self.in_group(&row.name, manufacturer, family))
+                        .map(|row| row.id)
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+        Ok(CarFilter { car_ids, category })
+    }
+
+    /// The BoP eras, oldest first.
+    pub(crate) fn bop_eras(&self) -> &[BopEra] {
+        &self.bop_eras
+    }
+
+    /// The era of the latest game version.
+    pub(crate) fn current_bop_era(&self) -> &str {
+        self.bop_eras.last().map_or("", |era| era.id.as_str())
+    }
+
+    /// The era to show laps from when `requested` is asked for: the current one by default, or
+    /// every era for `all`. Says so when the era isn't known.
+    pub(crate) fn bop_era_filter(&self, requested: Option<&str>) -> Result<Option<String>, String> {
+        match requested {
+            None => Ok(Some(self.current_bop_era().to_string())),
+            Some(era) if era.eq_ignore_ascii_case("all") => Ok(None),
+            Some(era)
+                if era == LEGACY_BOP_ERA || self.bop_eras.iter().any(|known| known.id == era) =>
+            {
+                Ok(Some(era.to_string()))
+            }
+            Some(era) => Err(format!("Unknown BoP era {era}")),
+        }
+    }
+
+    /// The era a version of the game belongs to. Recordings made before versions were recorded
+    /// could be from any era, so they're put in the legacy one.
+    pub(crate) fn bop_era(&self, game_version: &str) -> &str {
+        if game_version.is_empty() {
+            return LEGACY_BOP_ERA;
+        }
+        let version = version_parts(game_version);
+        self.bop_eras
+            .iter()
+            .rev()
+            .find(|era| version_parts(&era.game_version) <= version)
+            .or(self.bop_eras.first())
+            .map_or("", |era| era.id.as_str())
+    }
+
+    /// Like [`Catalogue::track_label`], for cars.
+    pub(crate) fn car_label(&self, id: &str, display_name: Option<&str>) -> String {
+        display_name
+            .or(self.car(id).map(|car| car.display_name.as_str()))
+            .unwrap_or(id)
+            .to_string()
+    }
+}
+
+/// Splits a version like `1.10.2` into numbers, so it sorts after `1.9`.
+fn version_parts(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| {
+            part.trim()
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The bundled catalogue with `overrides` added to it.
+    fn load(overrides: &str) -> Catalogue {
+        let path = std::env::temp_dir().join(format!(
+            "sim_leaderboards_catalogue_{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, overrides).unwrap();
+        let catalogue = Catalogue::load(Some(&path));
+        std::fs::remove_file(&path).unwrap();
+        catalogue.unwrap()
+    }
+
+    #[test]
+    fn overrides_replace_bundled_entries_with_the_same_id() {
+        let catalogue = load(
+            r#"{
+                "tracks": [{"id": "Spa", "display_name": "Spa-Francorchamps", "layout": "2024",
+                    "length_km": 7.004, "sector_count": 3, "country": "Belgium"}],
+                "cars": [{"id": "New_Car_GT3", "display_name": "New Car GT3", "category": "GT3",
+                    "manufacturer": "New", "family": "New Car GT3", "year": 2025,
+                    "bop_era": "2025"}],
+                "bop_eras": [{"id": "2025", "game_version": "1.11"},
+                    {"id": "2024", "game_version": "1.10.1"}]
+            }"#,
+        );
+
+        let spa = catalogue.track("spa").unwrap();
+        assert_eq!(
+            (spa.display_name.as_str(), spa.layout.as_str()),
+            ("Spa-Francorchamps", "2024")
+        );
+        assert_eq!(catalogue.track("monza").unwrap().display_name, "Monza");
+        assert_eq!(
+            catalogue.car("new_car_gt3").unwrap().display_name,
+            "New Car GT3"
+        );
+        assert_eq!(
+            catalogue.car("NEW_CAR_GT3").unwrap().display_name,
+            "New Car GT3"
+        );
+        assert_eq!(
+            catalogue.car("bmw_m4_gt3").unwrap().display_name,
+            "BMW M4 GT3"
+        );
+        assert_eq!(catalogue.current_bop_era(), "2025");
+        assert_eq!(catalogue.bop_era("1.10.0"), "2023");
+        assert_eq!(catalogue.bop_era("1.10.1"), "2024");
+        assert_eq!(
+            catalogue
+                .bop_eras()
+                .iter()
+                .filter(|era| era.id == "2024")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn game_versions_sort_by_number() {
+        assert_eq!(version_parts("1.10.2"), [1, 10, 2]);
+        assert!(version_parts("1.10") > version_parts("1.9"));
+        assert!(version_parts("1.9.3") > version_parts("1.9"));
+        assert_eq!(version_parts("1.8 beta"), [1, 8]);
+
+        let catalogue = Catalogue::load(None).unwrap();
+        assert_eq!(catalogue.bop_era("1.10"), "2024");
+        assert_eq!(catalogue.bop_era("1.10.2"), "2024");
+        assert_eq!(catalogue.bop_era("1.9.5"), "2023");
+        assert_eq!(catalogue.bop_era("1.1"), "2019");
+    }
+
+    #[test]
+    fn versions_outside_every_era_have_one_picked() {
+        let catalogue = Catalogue::load(None).unwrap();
+        assert_eq!(catalogue.bop_era(""), LEGACY_BOP_ERA);
+        assert_eq!(catalogue.bop_era("0.9"), "2018");
+        assert_eq!(catalogue.bop_era("2.0"), catalogue.current_bop_era());
+    }
+
+    #[test]
+    fn bop_era_filters() {
+        let catalogue = Catalogue::load(None).unwrap();
+        assert_eq!(catalogue.bop_era_filter(None), Ok(Some("2024".to_string())));
+        assert_eq!(catalogue.bop_era_filter(Some("ALL")), Ok(None));
+        assert_eq!(
+            catalogue.bop_era_filter(Some("2019")),
+            Ok(Some("2019".to_string()))
+        );
+        assert_eq!(
+            catalogue.bop_era_filter(Some(LEGACY_BOP_ERA)),
+            Ok(Some(LEGACY_BOP_ERA.to_string()))
+        );
+        assert_eq!(
+            catalogue.bop_era_filter(Some("1999")),
+            Err("Unknown BoP era 1999".to_string())
+        );
+    }
+
+    fn car(id: i64, name: &str, category: &str) -> CarRow {
+        CarRow {
+            id,
+            name: name.to_string(),
+            category: category.to_string(),
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn a_family_ranks_a_car_and_its_evos_together() {
+        let catalogue = Catalogue::load(None).unwrap();
+        let cars = [
+            car(1, "lamborghini_huracan_gt3", "GT3"),
+            car(2, "lamborghini_huracan_gt3_evo", "GT3"),
+            car(3, "lamborghini_huracan_gt3_evo2", "GT3"),
+            car(4, "lamborghini_huracan_st", "ST"),
+            car(5, "audi_r8_lms_evo", "GT3"),
+            car(6, "mod_car", "GT3"),
+        ];
+
+        let family = catalogue
+            .car_filter(&cars, None, None, None, Some("lamborghini huracan gt3"))
+            .unwrap();
+        assert_eq!(family.car_ids, Some(vec![1, 2, 3]));
+        let manufacturer = catalogue
+            .car_filter(&cars, None, Some("gt3"), Some("Lamborghini"), None)
+            .unwrap();
+        assert_eq!(manufacturer.car_ids, Some(vec![1, 2, 3, 4]));
+        assert_eq!(manufacturer.category.as_deref(), Some("GT3"));
+
+        let everything = catalogue.car_filter(&cars, None, None, None, None).unwrap();
+        assert_eq!(everything.car_ids, None);
+        let one = catalogue
+            .car_filter(
+                &cars,
+                Some("audi_r8_lms_evo"),
+                None,
+                Some("Lamborghini"),
+                None,
+            )
+            .unwrap();
+        assert_eq!(one.car_ids, Some(vec![5]));
+    }
+
+    #[test]
+    fn unknown_cars_and_groups_are_reported() {
+        let catalogue = Catalogue::load(None).unwrap();
+        let cars = [car(1, "bmw_m4_gt3", "GT3")];
+
+        assert_eq!(
+            catalogue
+                .car_filter(&cars, Some("bmw_m6_gt3"), None, None, None)
+                .err(),
+            Some(CarFilterError::Unknown(
+                "No laps have been recorded in bmw_m6_gt3".to_string()
+            ))
+        );
+        assert_eq!(
+            catalogue
+                .car_filter(&cars, None, None, Some("Trabant"), None)
+                .err(),
+            Some(CarFilterError::Unknown(
+                "Unknown manufacturer Trabant".to_string()
+            ))
+        );
+        assert_eq!(
+            catalogue
+                .car_filter(&cars, None, Some("F1"), None, None)
+                .err(),
+            Some(CarFilterError::Invalid("Unknown category F1".to_string()))
+        );
+    }
+}
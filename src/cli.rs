@@ -0,0 +1,375 @@
+use crate::cars::CarCategory;
+use crate::catalogue::Catalogue;
+use crate::format_lap_time;
+use crate::session::{ConditionsFilter, Surface};
+use crate::storage::{SqliteStorage, Storage};
+use crate::sync;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use std::net::SocketAddr;
+
+/// Tracks personal bests set in Assetto Corsa Competizione.
+#[derive(Parser)]
+#[command(version, about)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Wait for a session and track laps. This is the default.
+    Track,
+    /// Show each driver's best lap at a track.
+    Leaderboard(LeaderboardArgs),
+    /// Show a driver's best lap at every track and car they've driven.
+    Pbs(PbsArgs),
+    /// Show the record for every track and category.
+    Records(ConditionsArgs),
+    /// Serve leaderboards over HTTP.
+    Serve(ServeArgs),
+    /// List the tracks and cars the tracker doesn't recognise, and what they've been named.
+    Unrecognised,
+    /// Name a track the tracker doesn't recognise.
+    NameTrack(NameTrackArgs),
+    /// Name a car the tracker doesn't recognise, or give it a category.
+    NameCar(NameCarArgs),
+    /// Have Discord mention a driver when their records are beaten.
+    LinkDiscord(LinkDiscordArgs),
+    /// Copy the laps in a SQLite file into the database.
+    Sync(SyncArgs),
+}
+
+#[derive(Args)]
+pub(crate) struct SyncArgs {
+    /// The SQLite file to copy laps from, e.g. `sqlite:sim.db`.
+    pub(crate) from: String,
+}
+
+#[derive(Args)]
+pub(crate) struct NameTrackArgs {
+    /// The game's name for the track.
+    #[arg(long)]
+    track: String,
+    /// The name to show for it.
+    #[arg(long)]
+    display_name: String,
+}
+
+#[derive(Args)]
+pub(crate) struct NameCarArgs {
+    /// The game's name for the car.
+    #[arg(long)]
+    car: String,
+    /// The name to show for it.
+    #[arg(long, required_unless_present = "category")]
+    display_name: Option<String>,
+    /// The category to rank it in, e.g. GT3.
+    #[arg(long)]
+    category: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct LinkDiscordArgs {
+    /// The driver's name in the game.
+    #[arg(long)]
+    driver: String,
+    /// The Discord user to mention, e.g. 123456789012345678.
+    #[arg(long)]
+    discord_user_id: String,
+}
+
+#[derive(Args)]
+pub(crate) struct ServeArgs {
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub(crate) address: SocketAddr,
+}
+
+#[derive(Args)]
+pub(crate) struct LeaderboardArgs {
+    /// The game's name for the track, e.g. spa.
+    #[arg(long)]
+    track: String,
+    /// Rank laps set on this layout of the track rather than the latest.
+    #[arg(long)]
+    layout: Option<String>,
+    /// Only rank laps in this car, using the game's name for it, e.g. bmw_m4_gt3.
+    #[arg(long, conflicts_with_all = ["category", "manufacturer", "family"])]
+    car: Option<String>,
+    /// Only rank laps in cars of this category, e.g. GT3.
+    #[arg(long)]
+    category: Option<String>,
+    /// Only rank laps in cars made by this manufacturer, e.g. BMW.
+    #[arg(long)]
+    manufacturer: Option<String>,
+    /// Only rank laps in versions of this model, e.g. "McLaren 720S GT3" for it and its Evo.
+    #[arg(long)]
+    family: Option<String>,
+    #[command(flatten)]
+    conditions: ConditionsArgs,
+}
+
+#[derive(Args)]
+pub(crate) struct PbsArgs {
+    #[arg(long)]
+    driver: String,
+    #[command(flatten)]
+    conditions: ConditionsArgs,
+}
+
+#[derive(Args)]
+pub(crate) struct ConditionsArgs {
+    /// Show laps set in the wet rather than the dry.
+    #[arg(long)]
+    wet: bool,
+    /// Only show laps set with a track temperature from LOW up to HIGH °C.
+    #[arg(long, num_args = 2, value_names = ["LOW", "HIGH"])]
+    track_temperature: Option<Vec<f32>>,
+    /// Only show laps set in this BoP era rather than the current one, or in any with `all`.
+    #[arg(long)]
+    bop_era: Option<String>,
+}
+
+impl ConditionsArgs {
+    fn filter(&self, catalogue: &Catalogue) -> Result<ConditionsFilter> {
+        Ok(ConditionsFilter {
+            surface: if self.wet { Surface::Wet } else { Surface::Dry },
+            track_temperature: self
+                .track_temperature
+                .as_ref()
+                .map(|range| (range[0], range[1])),
+            bop_era: catalogue
+                .bop_era_filter(self.bop_era.as_deref())
+                .map_err(|error| anyhow!(error))?,
+        })
+    }
+}
+
+pub(crate) async fn leaderboard(
+    storage: &dyn Storage,
+    catalogue: &Catalogue,
+    args: LeaderboardArgs,
+) -> Result<()> {
+    let track = storage
+        .find_track(&args.track, args.layout.as_deref())
+        .await?
+        .with_context(|| format!("No laps have been recorded at {}", args.track))?;
+    let filter = catalogue
+        .car_filter(
+            &storage.cars().await?,
+            args.car.as_deref(),
+            args.category.as_deref(),
+            args.manufacturer.as_deref(),
+            args.family.as_deref(),
+        )
+        .map_err(|error| anyhow!(error))?;
+
+    let records = storage
+        .leaderboard(
+            track.id,
+            filter.car_ids.as_deref(),
+            filter.category.as_deref(),
+            &args.conditions.filter(catalogue)?,
+        )
+        .await?;
+    let leader = records.first().map(|r| r.lap_time_ms);
+    print_table(
+        &["Pos", "Driver", "Car", "Time", "Gap", "Set"],
+        records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                vec![
+                    format!("P{}", index + 1),
+                    record.driver_name.clone(),
+                    catalogue.car_label(&record.car_name, record.car_display_name.as_deref()),
+                    format_lap_time(Some(record.lap_time_ms)),
+                    match leader {
+                        Some(leader) if index > 0 => format!("+{}ms", record.lap_time_ms - leader),
+                        _ => "".to_string(),
+                    },
+                    record.created_at.format("%Y-%m-%d").to_string(),
+                ]
+            })
+            .collect(),
+    );
+    Ok(())
+}
+
+pub(crate) async fn pbs(storage: &dyn Storage, catalogue: &Catalogue, args: PbsArgs) -> Result<()> {
+    let conditions = args.conditions.filter(catalogue)?;
+    let pbs = storage.personal_bests(&args.driver, &conditions).await?;
+
+    print_table(
+        &["Track", "Car", "Category", "Time", "Set"],
+        pbs.into_iter()
+            .map(|pb| {
+                vec![
+                    catalogue.track_label(
+                        &pb.track_name,
+                        pb.track_display_name.as_deref(),
+                        &pb.track_layout,
+                    ),
+                    catalogue.car_label(&pb.car_name, pb.car_display_name.as_deref()),
+                    pb.category,
+                    format_lap_time(Some(pb.lap_time_ms)),
+                    pb.created_at.format("%Y-%m-%d").to_string(),
+                ]
+            })
+            .collect(),
+    );
+    Ok(())
+}
+
+pub(crate) async fn records(
+    storage: &dyn Storage,
+    catalogue: &Catalogue,
+    args: ConditionsArgs,
+) -> Result<()> {
+    let conditions = args.filter(catalogue)?;
+    let records = storage.records(&conditions).await?;
+
+    print_table(
+        &["Track", "Category", "Driver", "Car", "Time", "Set"],
+        records
+            .into_iter()
+            .map(|record| {
+                vec![
+                    catalogue.track_label(
+                        &record.track_name,
+                        record.track_display_name.as_deref(),
+                        &record.track_layout,
+                    ),
+                    record.category,
+                    record.driver_name,
+                    catalogue.car_label(&record.car_name, record.car_display_name.as_deref()),
+                    format_lap_time(Some(record.lap_time_ms)),
+                    record.created_at.format("%Y-%m-%d").to_string(),
+                ]
+            })
+            .collect(),
+    );
+    Ok(())
+}
+
+pub(crate) async fn unrecognised(storage: &dyn Storage, catalogue: &Catalogue) -> Result<()> {
+    let tracks = storage.tracks().await?;
+    let cars = storage.cars().await?;
+    let mut rows: Vec<Vec<String>> = tracks
+        .into_iter()
+        .filter(|track| catalogue.track(&track.name).is_none())
+        .map(|track| {
+            vec![
+                "Track".to_string(),
+                track.name,
+                track.display_name.unwrap_or_default(),
+                "".to_string(),
+            ]
+        })
+        .collect();
+    rows.extend(
+        cars.into_iter()
+            .filter(|car| catalogue.car(&car.name).is_none())
+            .map(|car| {
+                vec![
+                    "Car".to_string(),
+                    car.name,
+                    car.display_name.unwrap_or_default(),
+                    car.category,
+                ]
+            }),
+    );
+    if rows.is_empty() {
+        println!("Every track and car is recognised");
+        return Ok(());
+    }
+    print_table(&["Kind", "Name", "Display name", "Category"], rows);
+    Ok(())
+}
+
+pub(crate) async fn name_track(storage: &dyn Storage, args: NameTrackArgs) -> Result<()> {
+    if !storage.name_track(&args.track, &args.display_name).await? {
+        bail!("No sessions have been driven at {}", args.track);
+    }
+    Ok(())
+}
+
+pub(crate) async fn name_car(storage: &dyn Storage, args: NameCarArgs) -> Result<()> {
+    let category = match &args.category {
+        Some(category) => Some(parse_category(category)?),
+        None => None,
+    };
+    if !storage
+        .name_car(&args.car, args.display_name.as_deref(), category.as_ref())
+        .await?
+    {
+        bail!("No sessions have been driven in {}", args.car);
+    }
+    Ok(())
+}
+
+pub(crate) async fn link_discord(storage: &dyn Storage, args: LinkDiscordArgs) -> Result<()> {
+    if !storage
+        .link_discord_user(&args.driver, &args.discord_user_id)
+        .await?
+    {
+        bail!("No sessions have been driven by {}", args.driver);
+    }
+    Ok(())
+}
+
+pub(crate) async fn sync(storage: &dyn Storage, args: SyncArgs) -> Result<()> {
+    if !args.from.starts_with("sqlite:") {
+        bail!("Can only copy laps from a sqlite: URL, not {}", args.from);
+    }
+    let from = SqliteStorage::connect(&args.from).await?;
+    let synced = sync::sync(&from, storage).await?;
+    println!(
+        "Copied {} sessions and {} laps, and set {} best laps",
+        synced.sessions, synced.laps, synced.best_laps
+    );
+    Ok(())
+}
+
+fn parse_category(category: &str) -> Result<CarCategory> {
+    category
+        .to_uppercase()
+        .parse()
+        .map_err(|_| anyhow!("Unknown category {category}"))
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    if rows.is_empty() {
+        println!("No laps found");
+        return;
+    }
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(column, header)| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!(
+        "{}",
+        format_row(headers.iter().map(|h| h.to_string()).collect())
+    );
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
@@ -0,0 +1,547 @@
+use crate::catalogue::Catalogue;
+use crate::sectors::split_time;
+use crate::validity::InvalidReason;
+use crate::{format_lap_time, BestLapWithDriver, BestLaps, LastLap};
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::future::Future;
+use std::io::{stdout, IsTerminal};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Something that happened in the terminal.
+pub(crate) enum DashboardEvent {
+    Quit,
+    Resize,
+}
+
+/// One lap of the current session.
+pub(crate) struct SessionLap {
+    pub(crate) lap_number: i32,
+    pub(crate) lap_time_ms: i64,
+    pub(crate) invalid_reason: Option<InvalidReason>,
+}
+
+/// Everything the dashboard shows.
+#[derive(Default)]
+pub(crate) struct View {
+    pub(crate) title: String,
+    pub(crate) status: String,
+    /// Something going wrong that doesn't stop tracking, e.g. the database being unreachable.
+    pub(crate) warning: Option<String>,
+    /// The latest message passed in through an [`Announcer`].
+    pub(crate) announcement: Option<String>,
+    pub(crate) best_laps: BestLaps,
+    pub(crate) laps: Vec<SessionLap>,
+    pub(crate) last_lap: Option<LastLap>,
+    pub(crate) current_sectors: Vec<i64>,
+}
+
+/// Full-screen view of the session. Nothing is drawn when stdout isn't a terminal, so the tracker
+/// can still run headless, e.g. when replaying a recording.
+pub(crate) struct Dashboard {
+    terminal: Option<DefaultTerminal>,
+    events: mpsc::UnboundedReceiver<DashboardEvent>,
+    driver_id: i64,
+    /// How many of the fastest drivers each leaderboard always shows.
+    top_n: usize,
+    catalogue: Arc<Catalogue>,
+    announcer: Announcer,
+    announcements: mpsc::UnboundedReceiver<String>,
+    pub(crate) view: View,
+}
+
+/// Passes messages to the dashboard to show, e.g. from the stdout notifier, which would otherwise
+/// print over it. They're printed when there's no terminal to draw on.
+#[derive(Clone)]
+pub(crate) struct Announcer(Option<mpsc::UnboundedSender<String>>);
+
+impl Announcer {
+    pub(crate) fn announce(&self, message: String) {
+        match &self.0 {
+            // The dashboard only goes away once tracking stops, so there's nothing to show it on.
+            Some(sender) => drop(sender.send(message)),
+            None => println!("{message}"),
+        }
+    }
+}
+
+impl Dashboard {
+    pub(crate) fn start(
+        driver_id: i64,
+        top_n: usize,
+        catalogue: Arc<Catalogue>,
+    ) -> Result<Dashboard> {
+        Dashboard::new(driver_id, top_n, catalogue, stdout().is_terminal())
+    }
+
+    /// A dashboard that never draws, whatever stdout is.
+    #[cfg(test)]
+    pub(crate) fn headless(driver_id: i64, top_n: usize, catalogue: Arc<Catalogue>) -> Dashboard {
+        Dashboard::new(driver_id, top_n, catalogue, false).unwrap()
+    }
+
+    fn new(
+        driver_id: i64,
+        top_n: usize,
+        catalogue: Arc<Catalogue>,
+        draw: bool,
+    ) -> Result<Dashboard> {
+        let (sender, events) = mpsc::unbounded_channel();
+        let terminal = if draw {
+            std::thread::spawn(move || read_events(sender));
+            Some(ratatui::try_init()?)
+        } else {
+            None
+        };
+        let (sender, announcements) = mpsc::unbounded_channel();
+        let announcer = Announcer(terminal.is_some().then_some(sender));
+        Ok(Dashboard {
+            terminal,
+            events,
+            driver_id,
+            top_n,
+            catalogue,
+            announcer,
+            announcements,
+            view: View::default(),
+        })
+    }
+
+    pub(crate) fn announcer(&self) -> Announcer {
+        self.announcer.clone()
+    }
+
+    pub(crate) fn draw(&mut self) -> Result<()> {
+        while let Ok(announcement) = self.announcements.try_recv() {
+            self.view.announcement = Some(announcement);
+        }
+        if let Some(terminal) = self.terminal.as_mut() {
+            terminal.draw(|frame| {
+                render(
+                    frame,
+                    &self.view,
+                    self.driver_id,
+                    self.top_n,
+                    &self.catalogue,
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Shows a warning under the status, or prints it when there's no terminal to draw on.
+    pub(crate) fn warn(&mut self, warning: String) {
+        if self.terminal.is_none() {
+            eprintln!("{warning}");
+        }
+        self.view.warning = Some(warning);
+    }
+
+    /// Waits for `future`, redrawing whenever the terminal is resized. Returns `None` if the driver
+    /// quits first.
+    pub(crate) async fn wait_for<F: Future>(&mut self, future: F) -> Result<Option<F::Output>> {
+        tokio::pin!(future);
+        loop {
+            tokio::select! {
+                output = &mut future => return Ok(Some(output)),
+                event = self.events.recv(), if self.terminal.is_some() => match event {
+                    Some(DashboardEvent::Quit) => return Ok(None),
+                    Some(DashboardEvent::Resize) => self.draw()?,
+                    None => return Ok(Some(future.await)),
+                },
+            }
+        }
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        if self.terminal.is_some() {
+            ratatui::restore();
+        }
+    }
+}
+
+/// Forwards terminal events until the dashboard goes away. Raw mode swallows Ctrl+C, so it quits
+/// along with `q` and Esc.
+fn read_events(sender: mpsc::UnboundedSender<DashboardEvent>) {
+    while let Ok(event) = event::read() {
+        let event = match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => DashboardEvent::Quit,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    DashboardEvent::Quit
+                }
+                _ => continue,
+            },
+            Event::Resize(_, _) => DashboardEvent::Resize,
+            _ => continue,
+        };
+        if sender.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+fn render(frame: &mut Frame, view: &View, driver_id: i64, top_n: usize, catalogue: &Catalogue) {
+    let [header, leaderboards, session] = Layout::vertical([
+        Constraint::Length(4),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+    ])
+    .areas(frame.area());
+    let [car, category] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(leaderboards);
+    let [laps, live] =
+        Layout::horizontal([Constraint::Fill(2), Constraint::Fill(3)]).areas(session);
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(view.title.as_str()).bold().underlined(),
+            Line::from(view.status.as_str()),
+            Line::from(view.warning.as_deref().unwrap_or_default()).fg(Color::Yellow),
+            Line::from(view.announcement.as_deref().unwrap_or_default()).fg(Color::Cyan),
+        ]),
+        header,
+    );
+    render_leaderboard(
+        frame,
+        car,
+        "Car",
+        &view.best_laps.car.records,
+        driver_id,
+        top_n,
+        None,
+    );
+    render_leaderboard(
+        frame,
+        category,
+        "Category",
+        &view.best_laps.category.records,
+        driver_id,
+        top_n,
+        Some(catalogue),
+    );
+    render_laps(frame, laps, view);
+    frame.render_widget(
+        Paragraph::new(live_lines(view)).block(Block::bordered().title("Live")),
+        live,
+    );
+}
+
+/// Shows the top `top_n` rows plus the driver's own row and the rows either side of it. Leaderboards
+/// that mix cars are given the catalogue to label each row's car with.
+fn render_leaderboard(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    records: &[BestLapWithDriver],
+    driver_id: i64,
+    top_n: usize,
+    car_labels: Option<&Catalogue>,
+) {
+    let leader = records.first().map(|r| r.lap_time_ms);
+    let mine = records.iter().position(|r| r.driver_id == driver_id);
+    let mut rows = Vec::new();
+    let mut last_shown = None;
+    for (index, record) in records.iter().enumerate() {
+        if index >= top_n && mine.is_none_or(|mine| mine.abs_diff(index) > 1) {
+            continue;
+        }
+        if index > 0 && last_shown != Some(index - 1) {
+            rows.push(Row::new(["…"]));
+        }
+        last_shown = Some(index);
+        let gap = match leader {
+            Some(leader) if index > 0 => {
+                format!("+{}", format_seconds(record.lap_time_ms - leader))
+            }
+            _ => "".to_string(),
+        };
+        let mut cells = vec![
+            format!("P{}", index + 1),
+            record.driver_name.clone(),
+            format_lap_time(Some(record.lap_time_ms)),
+            gap,
+        ];
+        if let Some(catalogue) = car_labels {
+            cells.push(catalogue.car_label(&record.car_name, record.car_display_name.as_deref()));
+        }
+        let row = Row::new(cells);
+        rows.push(if Some(index) == mine {
+            row.style(Style::new().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else {
+            row
+        });
+    }
+
+    let mut widths = vec![
+        Constraint::Length(4),
+        Constraint::Fill(1),
+        Constraint::Length(9),
+        Constraint::Length(9),
+    ];
+    let mut header = vec!["Pos", "Driver", "Time", "Gap"];
+    if car_labels.is_some() {
+        widths.push(Constraint::Fill(1));
+        header.push("Car");
+    }
+    let mut block = Block::bordered().title(title);
+    if let Some(summary) = chase_summary(records, mine) {
+        block = block.title_bottom(summary);
+    }
+    frame.render_widget(
+        Table::new(rows, widths)
+            .header(Row::new(header).bold())
+            .block(block),
+        area,
+    );
+}
+
+/// Where the driver stands and who they're chasing, e.g. `P7 of 23, +0.412 to P6 (Alice)`.
+fn chase_summary(records: &[BestLapWithDriver], mine: Option<usize>) -> Option<String> {
+    let mine = mine?;
+    let my_time = records[mine].lap_time_ms;
+    let position = format!("P{} of {}", mine + 1, records.len());
+    Some(match mine.checked_sub(1) {
+        Some(ahead) => format!(
+            "{position}, +{} to P{} ({})",
+            format_seconds(my_time - records[ahead].lap_time_ms),
+            ahead + 1,
+            records[ahead].driver_name
+        ),
+        None => match records.get(1) {
+            Some(behind) => format!(
+                "{position}, {} clear of P2 ({})",
+                format_seconds(behind.lap_time_ms - my_time),
+                behind.driver_name
+            ),
+            None => position,
+        },
+    })
+}
+
+/// A gap between two laps in seconds, e.g. `0.412`.
+fn format_seconds(ms: i64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+fn render_laps(frame: &mut Frame, area: Rect, view: &View) {
+    let car_record = view.best_laps.car.overall.as_ref().map(|r| r.lap_time_ms);
+    // Newest first, so the latest lap is visible however long the session runs.
+    let rows = view.laps.iter().rev().map(|lap| {
+        let (style, validity) = match lap.invalid_reason {
+            Some(reason) => (Style::new().fg(Color::Red), format!("Invalid ({reason})")),
+            None => (Style::new(), "".to_string()),
+        };
+        Row::new(vec![
+            lap.lap_number.to_string(),
+            format_lap_time(Some(lap.lap_time_ms)),
+            car_record
+                .map(|t| format!("{:+}ms", lap.lap_time_ms - t))
+                .unwrap_or("∞".to_string()),
+            validity,
+        ])
+        .style(style)
+    });
+    frame.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Length(4),
+                Constraint::Length(9),
+                Constraint::Length(10),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["Lap", "Time", "Car diff", ""]).bold())
+        .block(Block::bordered().title("Session")),
+        area,
+    );
+}
+
+fn live_lines(view: &View) -> Vec<Line<'static>> {
+    let laps = &view.best_laps;
+    let mut lines = vec![Line::from(format!("Conditions: {}", laps.conditions))];
+
+    let is_my_lap_for_car_fastest = laps.car.mine.as_ref().is_some_and(|mine| {
+        laps.car
+            .overall
+            .as_ref()
+            .is_some_and(|overall| mine.id == overall.id)
+    });
+    lines.push(if is_my_lap_for_car_fastest {
+        Line::from(format!(
+            "You are fastest in this car: {}",
+            format_lap_time(laps.car.mine.clone()),
+        ))
+        .green()
+    } else if let Some(mine) = &laps.car.mine {
+        Line::from(format!(
+            "Car PB: {} Best: {} Diff: {}ms",
+            format_lap_time(Some(mine.lap_time_ms)),
+            format_lap_time(laps.car.overall.clone()),
+            mine.lap_time_ms
+                - laps
+                    .car
+                    .overall
+                    .as_ref()
+                    .map(|t| t.lap_time_ms)
+                    .unwrap_or(0)
+        ))
+        .blue()
+    } else if laps.car.overall.is_some() {
+        Line::from(format!(
+            "No PB in this car. Best: {}",
+            format_lap_time(laps.car.overall.clone()),
+        ))
+        .red()
+    } else {
+        Line::from("There are no lap times in this car").magenta()
+    });
+
+    let is_my_lap_for_category_fastest = laps.category.mine.as_ref().is_some_and(|mine| {
+        laps.category
+            .overall
+            .as_ref()
+            .is_some_and(|overall| mine.id == overall.id)
+    });
+    lines.push(if is_my_lap_for_category_fastest {
+        Line::from(format!(
+            "You are fastest in this category {}",
+            format_lap_time(laps.category.mine.clone())
+        ))
+        .green()
+    } else if let Some(mine) = &laps.category.mine {
+        Line::from(format!(
+            "Category PB: {} Best: {} Diff: {}ms",
+            format_lap_time(Some(mine.lap_time_ms)),
+            format_lap_time(laps.category.overall.clone()),
+            mine.lap_time_ms
+                - laps
+                    .category
+                    .overall
+                    .as_ref()
+                    .map(|t| t.lap_time_ms)
+                    .unwrap_or(0)
+        ))
+        .blue()
+    } else if laps.category.overall.is_some() {
+        Line::from(format!(
+            "No PB in this category. Best: {}",
+            format_lap_time(laps.category.overall.clone()),
+        ))
+        .red()
+    } else {
+        Line::from("No lap times in this category").magenta()
+    });
+
+    lines.push(match &view.last_lap {
+        Some(last_lap) => {
+            let last_lap_ms = last_lap.lap_time_ms;
+            let line = Line::from(format!(
+                "Last: {}{} Car diff: {}ms Category diff {}ms",
+                format_lap_time(Some(last_lap_ms)),
+                last_lap
+                    .invalid_reason
+                    .map(|reason| format!(" Invalid ({})", reason))
+                    .unwrap_or_default(),
+                laps.car
+                    .overall
+                    .as_ref()
+                    .map(|t| (last_lap_ms - t.lap_time_ms).to_string())
+                    .unwrap_or("∞".to_string()),
+                laps.category
+                    .overall
+                    .as_ref()
+                    .map(|t| (last_lap_ms - t.lap_time_ms).to_string())
+                    .unwrap_or("∞".to_string())
+            ));
+            if last_lap.invalid_reason.is_some() {
+                line.red()
+            } else {
+                line
+            }
+        }
+        None => Line::from("Stop staring at me & start driving"),
+    });
+
+    let sector_count = view.current_sectors.len();
+    lines.push(match split_time(&view.current_sectors, sector_count) {
+        Some(split) => Line::from(format!(
+            "S{}: {} Car record diff: {}ms Category record diff: {}ms",
+            sector_count,
+            format_lap_time(Some(split)),
+            split_time(&laps.car.overall_sectors, sector_count)
+                .map(|t| (split - t).to_string())
+                .unwrap_or("∞".to_string()),
+            split_time(&laps.category.overall_sectors, sector_count)
+                .map(|t| (split - t).to_string())
+                .unwrap_or("∞".to_string())
+        )),
+        None => Line::from(format!(
+            "Theoretical best: {}",
+            format_lap_time(laps.theoretical_best)
+        )),
+    });
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standings(laps: &[(&str, i64)]) -> Vec<BestLapWithDriver> {
+        laps.iter()
+            .enumerate()
+            .map(|(index, &(driver, lap_time_ms))| BestLapWithDriver {
+                id: index as i64 + 1,
+                driver_id: index as i64 + 1,
+                track_id: 1,
+                created_at: chrono::Utc::now(),
+                lap_time_ms,
+                driver_name: driver.to_string(),
+                car_id: 1,
+                car_name: "bmw_m4_gt3".to_string(),
+                car_display_name: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_leader_is_told_their_lead() {
+        let records = standings(&[("Alice", 130000), ("Bob", 131050), ("Carol", 132000)]);
+        assert_eq!(
+            chase_summary(&records, Some(0)).as_deref(),
+            Some("P1 of 3, 1.050 clear of P2 (Bob)")
+        );
+        let alone = standings(&[("Alice", 130000)]);
+        assert_eq!(chase_summary(&alone, Some(0)).as_deref(), Some("P1 of 1"));
+    }
+
+    #[test]
+    fn the_pack_is_told_who_to_chase() {
+        let records = standings(&[("Alice", 130000), ("Bob", 130412), ("Carol", 132000)]);
+        assert_eq!(
+            chase_summary(&records, Some(1)).as_deref(),
+            Some("P2 of 3, +0.412 to P1 (Alice)")
+        );
+        assert_eq!(
+            chase_summary(&records, Some(2)).as_deref(),
+            Some("P3 of 3, +1.588 to P2 (Bob)")
+        );
+    }
+
+    #[test]
+    fn drivers_without_a_lap_have_no_summary() {
+        let records = standings(&[("Alice", 130000)]);
+        assert_eq!(chase_summary(&records, None), None);
+        assert_eq!(chase_summary(&[], None), None);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::notifier::{PersonalBest, Position};
+use crate::session::{Conditions, SessionType};
+use crate::storage::Storage;
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Something that happened while tracking laps.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event {
+    SessionStarted(Session),
+    LapCompleted(LapCompleted),
+    PersonalBest(PersonalBest),
+    CarRecord(RecordSet),
+    CategoryRecord(RecordSet),
+    /// Where the driver stands, published whenever the tracker's leaderboards change.
+    Standings(Standings),
+    SessionEnded(Session),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Session {
+    pub(crate) driver: String,
+    pub(crate) track: String,
+    pub(crate) car: String,
+    pub(crate) category: String,
+    pub(crate) session_type: SessionType,
+    pub(crate) conditions: Conditions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LapCompleted {
+    pub(crate) driver: String,
+    pub(crate) track: String,
+    pub(crate) car: String,
+    pub(crate) category: String,
+    pub(crate) lap_number: i32,
+    pub(crate) lap_time_ms: i64,
+    pub(crate) is_valid: bool,
+    pub(crate) invalid_reason: Option<String>,
+    /// Empty when the sectors couldn't be timed, e.g. after joining mid-lap.
+    pub(crate) sectors_ms: Vec<i64>,
+}
+
+/// A lap that's now the fastest for its car or category.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RecordSet {
+    pub(crate) driver: String,
+    pub(crate) track: String,
+    pub(crate) car: String,
+    pub(crate) category: String,
+    pub(crate) conditions: String,
+    pub(crate) lap_time_ms: i64,
+    pub(crate) previous_driver: String,
+    pub(crate) previous_lap_time_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Standings {
+    pub(crate) driver: String,
+    pub(crate) track: String,
+    pub(crate) car: String,
+    pub(crate) category: String,
+    pub(crate) conditions: String,
+    pub(crate) car_standing: Standing,
+    pub(crate) category_standing: Standing,
+    pub(crate) last_lap: Option<LastLap>,
+}
+
+/// A driver's place on one leaderboard.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Standing {
+    pub(crate) personal_best_ms: Option<i64>,
+    pub(crate) position: Option<Position>,
+    pub(crate) record: Option<Record>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Record {
+    pub(crate) driver: String,
+    pub(crate) lap_time_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LastLap {
+    pub(crate) lap_time_ms: i64,
+    pub(crate) invalid_reason: Option<String>,
+}
+
+/// Publishes events through the storage, so every `serve` sharing it can stream them, wherever
+/// the tracker runs.
+pub(crate) struct EventBus {
+    storage: Arc<dyn Storage>,
+}
+
+impl EventBus {
+    pub(crate) fn new(storage: Arc<dyn Storage>) -> EventBus {
+        EventBus { storage }
+    }
+
+    pub(crate) async fn publish(&self, event: Event) -> Result<()> {
+        self.storage
+            .publish_event(&serde_json::to_string(&event)?)
+            .await
+    }
+}
@@ -1,26 +1,35 @@
 mod r#cars;
-mod r#track;
+mod r#catalogue;
+mod r#cli;
+mod r#dashboard;
+mod r#events;
+mod r#notifier;
+mod r#sectors;
+mod r#server;
+mod r#session;
+mod r#storage;
+mod r#sync;
+mod r#telemetry;
+mod r#validity;
 
-use crate::track::TrackName;
 use anyhow::Result;
-use crossterm::cursor::{MoveToNextLine, MoveToPreviousLine};
-use crossterm::style::{Attribute, SetAttribute};
-use crossterm::terminal::{Clear, ClearType};
-use crossterm::{
-    cursor,
-    style::{Color, Print, ResetColor, SetForegroundColor},
-    ExecutableCommand,
-};
-use discord_webhook2::message::Message;
-use discord_webhook2::webhook::DiscordWebhook;
+use clap::Parser;
 use dotenv::dotenv;
-use r#cars::Car;
-use simetry::assetto_corsa_competizione;
-use simetry::assetto_corsa_competizione::Time;
-use sqlx::postgres::PgPoolOptions;
-use sqlx::{Pool, Postgres};
+use r#cars::CarCategory;
+use r#catalogue::Catalogue;
+use r#cli::{Cli, Command};
+use r#dashboard::{Dashboard, SessionLap, View};
+use r#events::{Event, EventBus, LapCompleted, RecordSet, Standing, Standings};
+use r#notifier::{Notifier, PbTier, PersonalBest, Position, RecordBeaten, RecordKind};
+use r#sectors::SectorTimer;
+use r#session::{ConditionsFilter, Surface};
+use r#storage::{NewLap, NewSession, Storage, TrackLayout};
+use r#telemetry::{AccSource, Recorder, Recording, Replay, TelemetrySource};
+use r#validity::{InvalidReason, LapValidity};
+use serde::{Deserialize, Serialize};
 use std::env;
-use std::io::{stdout, Stdout, Write};
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(sqlx::FromRow, Clone, Debug)]
@@ -30,31 +39,99 @@ struct Driver {
 }
 
 #[allow(dead_code)]
-#[derive(sqlx::FromRow, Clone, Debug)]
+#[derive(sqlx::FromRow, Serialize, Clone, Debug)]
 struct TrackRow {
     id: i64,
     name: String,
+    /// Set by an admin for tracks the tracker doesn't recognise.
+    #[serde(skip)]
+    display_name: Option<String>,
+    layout: String,
+    length_km: Option<f64>,
+    sector_count: Option<i32>,
+    country: Option<String>,
 }
 
 #[allow(dead_code)]
-#[derive(sqlx::FromRow, Clone, Debug)]
+#[derive(sqlx::FromRow, Serialize, Clone, Debug)]
 struct CarRow {
     id: i64,
     name: String,
     category: String, // Store category as a string
+    /// Set by an admin for cars the tracker doesn't recognise.
+    #[serde(skip)]
+    display_name: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, Serialize)]
+struct PersonalBestRow {
+    track_name: String,
+    track_display_name: Option<String>,
+    track_layout: String,
+    car_name: String,
+    car_display_name: Option<String>,
+    category: String,
+    lap_time_ms: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, Serialize)]
+struct CategoryRecordRow {
+    track_name: String,
+    track_display_name: Option<String>,
+    track_layout: String,
+    category: String,
+    driver_name: String,
+    car_name: String,
+    car_display_name: Option<String>,
+    lap_time_ms: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, Serialize)]
+struct RecordSetRow {
+    track_name: String,
+    track_display_name: Option<String>,
+    track_layout: String,
+    category: String,
+    surface: Surface,
+    driver_name: String,
+    car_name: String,
+    car_display_name: Option<String>,
+    lap_time_ms: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, Serialize)]
+struct RecentPersonalBestRow {
+    driver_name: String,
+    track_name: String,
+    track_display_name: Option<String>,
+    track_layout: String,
+    car_name: String,
+    car_display_name: Option<String>,
+    category: String,
+    surface: Surface,
+    lap_time_ms: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
 }
 
 trait LapTime {
     fn lap_time_ms(&self) -> i64;
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct BestLapData {
     driver_id: i64,
     track_id: i64,
     created_at: chrono::DateTime<chrono::Utc>,
     lap_time_ms: i64,
     car_id: i64,
+    lap_id: i64,
+    surface: Surface,
+    /// Missing from best laps queued before eras were recorded.
+    #[serde(default = "catalogue::legacy_bop_era")]
+    bop_era: String,
 }
 
 impl LapTime for BestLapData {
@@ -72,6 +149,9 @@ struct BestLap {
     created_at: chrono::DateTime<chrono::Utc>,
     lap_time_ms: i64,
     car_id: i64,
+    lap_id: i64,
+    surface: Surface,
+    bop_era: String,
 }
 
 impl LapTime for BestLapWithDriver {
@@ -81,7 +161,7 @@ impl LapTime for BestLapWithDriver {
 }
 
 #[allow(dead_code)]
-#[derive(sqlx::FromRow, Clone)]
+#[derive(sqlx::FromRow, Serialize, Clone)]
 struct BestLapWithDriver {
     id: i64,
     driver_id: i64,
@@ -90,12 +170,75 @@ struct BestLapWithDriver {
     lap_time_ms: i64,
     driver_name: String,
     car_id: i64,
+    car_name: String,
+    car_display_name: Option<String>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 struct MyLapAndBestLap {
     mine: Option<BestLapWithDriver>,
     overall: Option<BestLapWithDriver>,
+    overall_sectors: Vec<i64>,
+    /// Every driver's best lap, fastest first.
+    records: Vec<BestLapWithDriver>,
+}
+
+impl MyLapAndBestLap {
+    fn standing(&self) -> Standing {
+        Standing {
+            personal_best_ms: self.mine.as_ref().map(|t| t.lap_time_ms),
+            position: self.mine.as_ref().and_then(|mine| {
+                self.records
+                    .iter()
+                    .position(|r| r.id == mine.id)
+                    .map(|index| Position {
+                        position: index + 1,
+                        entries: self.records.len(),
+                    })
+            }),
+            record: self.overall.as_ref().map(|t| events::Record {
+                driver: t.driver_name.clone(),
+                lap_time_ms: t.lap_time_ms,
+            }),
+        }
+    }
+
+    /// Puts a new personal best in the standings, unless the driver already has a faster lap in
+    /// them.
+    fn insert_personal_best(&mut self, lap: &BestLapWithDriver, sectors: &[i64]) {
+        if self
+            .mine
+            .as_ref()
+            .is_some_and(|mine| mine.lap_time_ms <= lap.lap_time_ms)
+        {
+            return;
+        }
+        self.records.retain(|r| r.driver_id != lap.driver_id);
+        let index = self
+            .records
+            .iter()
+            .position(|r| r.lap_time_ms > lap.lap_time_ms)
+            .unwrap_or(self.records.len());
+        self.records.insert(index, lap.clone());
+        if index == 0 {
+            self.overall = Some(lap.clone());
+            self.overall_sectors = sectors.to_vec();
+        }
+        self.mine = Some(lap.clone());
+    }
+
+    /// Where a lap by `driver_id` would place against everyone else's best.
+    fn position_for(&self, driver_id: i64, lap_time_ms: i64) -> Position {
+        let others = self.records.iter().filter(|r| r.driver_id != driver_id);
+        Position {
+            position: others
+                .clone()
+                .filter(|r| r.lap_time_ms <= lap_time_ms)
+                .count()
+                + 1,
+            entries: others.count() + 1,
+        }
+    }
 }
 
 impl LapTime for BestLap {
@@ -104,146 +247,313 @@ impl LapTime for BestLap {
     }
 }
 
+impl LapTime for i64 {
+    fn lap_time_ms(&self) -> i64 {
+        *self
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SessionRow {
+    id: i64,
+    session_index: i32,
+}
+
 #[derive(Clone)]
+struct LastLap {
+    lap_time_ms: i64,
+    invalid_reason: Option<InvalidReason>,
+}
+
+#[derive(Clone, Default)]
 struct BestLaps {
+    conditions: ConditionsFilter,
     car: MyLapAndBestLap,
     category: MyLapAndBestLap,
-}
-
-const LINE_LENGTH: usize = 80;
-
-fn pad_string(input: String) -> String {
-    if input.len() > LINE_LENGTH {
-        input[..LINE_LENGTH].to_string()
-    } else {
-        let mut padded = String::from(input.clone());
-        padded.extend(std::iter::repeat(' ').take(LINE_LENGTH - input.len()));
-        padded
-    }
+    theoretical_best: Option<i64>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&*env::var("DATABASE_URL").expect("DATABASE_URL is not set"))
-        .await?;
+    let cli = Cli::parse();
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+    let catalogue_path = env::var("CATALOGUE_PATH").ok();
+    let catalogue = Arc::new(Catalogue::load(catalogue_path.as_deref().map(Path::new))?);
+    let storage = match cli.command {
+        None | Some(Command::Track) => {
+            let queue_path =
+                env::var("OFFLINE_QUEUE_PATH").unwrap_or_else(|_| "offline_queue.db".to_string());
+            storage::connect_queued(&database_url, Path::new(&queue_path)).await?
+        }
+        _ => storage::connect(&database_url).await?,
+    };
+
+    match cli.command {
+        None | Some(Command::Track) => track(storage, catalogue).await,
+        Some(Command::Leaderboard(args)) => {
+            cli::leaderboard(storage.as_ref(), &catalogue, args).await
+        }
+        Some(Command::Pbs(args)) => cli::pbs(storage.as_ref(), &catalogue, args).await,
+        Some(Command::Records(args)) => cli::records(storage.as_ref(), &catalogue, args).await,
+        Some(Command::Serve(args)) => server::serve(storage, catalogue, args.address).await,
+        Some(Command::Unrecognised) => cli::unrecognised(storage.as_ref(), &catalogue).await,
+        Some(Command::NameTrack(args)) => cli::name_track(storage.as_ref(), args).await,
+        Some(Command::NameCar(args)) => cli::name_car(storage.as_ref(), args).await,
+        Some(Command::LinkDiscord(args)) => cli::link_discord(storage.as_ref(), args).await,
+        Some(Command::Sync(args)) => cli::sync(storage.as_ref(), args).await,
+    }
+}
 
-    let discord_webhook_url = env::var("DISCORD_WEBHOOK").expect("DISCORD_WEBHOOK is not set");
-    let discord_webhook: DiscordWebhook =
-        DiscordWebhook::new(discord_webhook_url).expect("Invalid webhook");
+async fn track(storage: Arc<dyn Storage>, catalogue: Arc<Catalogue>) -> Result<()> {
     let driver_name = env::var("DRIVER_NAME").expect("DRIVER_NAME is not set");
-    let driver = sqlx::query_as!(
-        Driver,
-        "INSERT INTO driver (name) VALUES ($1) ON CONFLICT (name) DO UPDATE set name=$1 RETURNING *",
-        driver_name
+    let track_temperature_band = env::var("TRACK_TEMPERATURE_BAND")
+        .ok()
+        .map(|s| s.parse::<f32>().expect("Invalid TRACK_TEMPERATURE_BAND"));
+    let driver = storage.upsert_driver(&driver_name).await?;
+
+    let mut source: Box<dyn TelemetrySource + Send> = match env::var("TELEMETRY_REPLAY_PATH") {
+        Ok(path) => {
+            let speed = env::var("TELEMETRY_REPLAY_SPEED")
+                .map(|s| s.parse().expect("Invalid TELEMETRY_REPLAY_SPEED"))
+                .unwrap_or(1.0);
+            Box::new(Replay::open(Path::new(&path), speed)?)
+        }
+        Err(_) => match env::var("TELEMETRY_RECORD_PATH") {
+            Ok(path) => Box::new(Recording::new(
+                AccSource::new(),
+                Recorder::create(Path::new(&path))?,
+            )),
+            Err(_) => Box::new(AccSource::new()),
+        },
+    };
+
+    let leaderboard_top_n = env::var("LEADERBOARD_TOP_N")
+        .map(|s| s.parse().expect("Invalid LEADERBOARD_TOP_N"))
+        .unwrap_or(5);
+    let dashboard = Dashboard::start(driver.id, leaderboard_top_n, catalogue.clone())?;
+    let notifier = notifier::from_env(dashboard.announcer())?;
+    track_laps(
+        storage,
+        catalogue,
+        source.as_mut(),
+        &driver,
+        track_temperature_band,
+        dashboard,
+        notifier.as_ref(),
     )
-    .fetch_one(&pool)
-    .await?;
+    .await
+}
 
+/// Tracks the laps `source` reads until it runs out of sessions or the driver quits.
+async fn track_laps(
+    storage: Arc<dyn Storage>,
+    catalogue: Arc<Catalogue>,
+    source: &mut (dyn TelemetrySource + Send),
+    driver: &Driver,
+    track_temperature_band: Option<f32>,
+    mut dashboard: Dashboard,
+    notifier: &(dyn Notifier + Send + Sync),
+) -> Result<()> {
+    let events = EventBus::new(storage.clone());
     loop {
-        let mut printer = stdout();
-        printer.execute(Clear(ClearType::All))?;
-        printer
-            .execute(MoveToPreviousLine(5))?
-            .execute(Print(pad_string(format!(
-                "Welcome {}, start a session to begin...",
-                driver.name,
-            ))))?
-            .flush()?;
-
-        let mut client = assetto_corsa_competizione::Client::connect(Duration::from_secs(1)).await;
-        let track_name = client.static_data().track.clone();
-        let track: TrackName = track_name.parse().expect("Unknown track");
-
-        let track_row = sqlx::query_as::<_, TrackRow>(
-            "INSERT INTO track (name) VALUES ($1) ON CONFLICT (name) DO UPDATE set name=$1 RETURNING *",
-        )
-        .bind(&track_name)
-        .fetch_one(&pool)
-        .await?;
-
-        let car_model = client.static_data().car_model.clone();
-        let car = Car::from_str(car_model.as_str()).expect("Unknown car model");
-        let car_row = sqlx::query_as!(
-            CarRow,
-            "INSERT INTO car (name, category) VALUES ($1, $2) ON CONFLICT (name) DO UPDATE set name=$1 RETURNING *",
-            &car_model,
-            &car.category.to_string()
-        )
-            .fetch_one(&pool)
-            .await?;
-
-        printer
-            .execute(cursor::MoveToColumn(0))?
-            .execute(SetAttribute(Attribute::Bold))?
-            .execute(SetAttribute(Attribute::Underlined))?
-            .execute(Print(pad_string(format!(
-                "{} ({}) on {}",
-                car.name, car.category, track
-            ))))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?
-            .flush()?;
+        dashboard.view = View {
+            title: format!("Welcome {}, start a session to begin...", driver.name),
+            ..View::default()
+        };
+        dashboard.draw()?;
+
+        let Some(session) = dashboard.wait_for(source.next_session()).await? else {
+            return Ok(());
+        };
+        let Some(session) = session? else {
+            return Ok(());
+        };
+        let layout = match catalogue.track(&session.track) {
+            Some(track) => TrackLayout {
+                layout: track.layout.clone(),
+                length_km: Some(track.length_km),
+                sector_count: Some(track.sector_count),
+                country: Some(track.country.clone()),
+            },
+            None => TrackLayout {
+                sector_count: Some(session.sector_count),
+                ..TrackLayout::default()
+            },
+        };
+        let track_row = storage.upsert_track(&session.track, &layout).await?;
+        // Cars from content released after this version are tracked until an admin categorises
+        // them.
+        let category = catalogue
+            .car(&session.car)
+            .map(|car| car.category.clone())
+            .unwrap_or(CarCategory::UNKNOWN);
+        let car_row = storage.upsert_car(&session.car, &category).await?;
+        let track = catalogue.track_label(
+            &track_row.name,
+            track_row.display_name.as_deref(),
+            &track_row.layout,
+        );
+        let car = catalogue.car_label(&car_row.name, car_row.display_name.as_deref());
+        let category = car_row.category.clone();
+        let bop_era = catalogue.bop_era(&session.game_version).to_string();
+
+        dashboard.view.title = format!("{car} ({category}) on {track}");
+        let unrecognised = unrecognised_warning(&catalogue, &track_row, &car_row);
+        if let Some(warning) = &unrecognised {
+            dashboard.warn(warning.clone());
+        }
 
+        let mut session_row: Option<SessionRow> = None;
+        let mut session_event: Option<events::Session> = None;
         let mut lap_number = 0;
-        let mut best_laps = refresh_laps(
-            &pool,
-            &driver,
-            &track_row,
-            &car_row,
-            None,
-            true,
-            printer.by_ref(),
-        )
-        .await?;
+        let mut lap_validity = LapValidity::default();
+        let mut last_lap_invalid_reason = None;
+        let mut sector_timer = SectorTimer::new(session.sector_count);
+        let mut best_laps = BestLaps::default();
+        let mut quit = false;
+
+        loop {
+            let Some(state) = dashboard.wait_for(source.next_state()).await? else {
+                quit = true;
+                break;
+            };
+            let Some(state) = state? else {
+                break;
+            };
+            let mut redraw = false;
+            if session_row
+                .as_ref()
+                .is_none_or(|s| s.session_index != state.session_index)
+            {
+                if let Some(previous) = session_row.take() {
+                    storage.end_session(previous.id, chrono::Utc::now()).await?;
+                }
+                if let Some(previous) = session_event.take() {
+                    events.publish(Event::SessionEnded(previous)).await?;
+                }
+                let session_id = storage
+                    .start_session(&NewSession {
+                        driver_id: driver.id,
+                        track_id: track_row.id,
+                        car_id: car_row.id,
+                        session_index: state.session_index,
+                        session_type: state.session_type,
+                        conditions: state.conditions,
+                        started_at: chrono::Utc::now(),
+                    })
+                    .await?;
+                session_row = Some(SessionRow {
+                    id: session_id,
+                    session_index: state.session_index,
+                });
+                let started = events::Session {
+                    driver: driver.name.clone(),
+                    track: track.clone(),
+                    car: car.clone(),
+                    category: category.clone(),
+                    session_type: state.session_type,
+                    conditions: state.conditions,
+                };
+                events
+                    .publish(Event::SessionStarted(started.clone()))
+                    .await?;
+                session_event = Some(started);
+                lap_number = state.completed_laps;
+                lap_validity = LapValidity::default();
+                last_lap_invalid_reason = None;
+                sector_timer = SectorTimer::new(session.sector_count);
+                dashboard.view.laps.clear();
+                redraw = true;
+            }
+            let session_id = session_row.as_ref().map(|s| s.id).unwrap_or_default();
+
+            let conditions =
+                ConditionsFilter::new(&state.conditions, track_temperature_band, &bop_era);
+            if redraw || conditions != best_laps.conditions {
+                best_laps = refresh_laps_or_keep(
+                    storage.as_ref(),
+                    &mut dashboard,
+                    driver,
+                    &track_row,
+                    &car_row,
+                    best_laps,
+                    conditions.clone(),
+                )
+                .await?;
+                redraw = true;
+            }
 
-        while let Some(sim_state) = client.next_sim_state().await {
             let mut refresh = false;
-            if sim_state.graphics.completed_laps.gt(&lap_number) {
-                lap_number = sim_state.graphics.completed_laps;
+            if state.completed_laps.gt(&lap_number) {
+                lap_number = state.completed_laps;
                 refresh = true;
-
-                if sim_state.graphics.lap_timing.best.millis < i32::MAX {
-                    if best_laps.car.mine.clone().is_none()
-                        || (best_laps.car.mine.clone().is_some_and(|t| {
-                            sim_state.graphics.lap_timing.best.millis < t.lap_time_ms as i32
+                dashboard.view.warning = unrecognised.clone();
+
+                let completed_lap = lap_validity.finish_lap();
+                last_lap_invalid_reason = completed_lap.reason();
+                if let Some(lap_time_ms) = state.last_lap_ms {
+                    dashboard.view.laps.push(SessionLap {
+                        lap_number,
+                        lap_time_ms,
+                        invalid_reason: completed_lap.reason(),
+                    });
+                    let sectors = sector_timer.finish_lap(lap_time_ms).unwrap_or_default();
+                    let lap_id = storage
+                        .insert_lap(&NewLap {
+                            driver_id: driver.id,
+                            track_id: track_row.id,
+                            car_id: car_row.id,
+                            session_id,
+                            lap_number,
+                            lap_time_ms,
+                            is_valid: completed_lap.is_valid(),
+                            invalid_reason: completed_lap.reason().map(|r| r.to_string()),
+                            surface: conditions.surface,
+                            track_temperature: state.conditions.track_temperature,
+                            sectors_ms: sectors.clone(),
+                            created_at: chrono::Utc::now(),
+                            game_version: Some(session.game_version.clone())
+                                .filter(|version| !version.is_empty()),
+                            bop_era: bop_era.clone(),
+                        })
+                        .await?;
+                    events
+                        .publish(Event::LapCompleted(LapCompleted {
+                            driver: driver.name.clone(),
+                            track: track.clone(),
+                            car: car.clone(),
+                            category: category.clone(),
+                            lap_number,
+                            lap_time_ms,
+                            is_valid: completed_lap.is_valid(),
+                            invalid_reason: completed_lap.reason().map(|r| r.to_string()),
+                            sectors_ms: sectors.clone(),
                         }))
+                        .await?;
+
+                    if completed_lap.is_valid()
+                        && (best_laps.car.mine.clone().is_none()
+                            || (best_laps
+                                .car
+                                .mine
+                                .clone()
+                                .is_some_and(|t| lap_time_ms < t.lap_time_ms)))
                     {
                         let new_best_time = BestLapData {
                             driver_id: driver.id,
                             track_id: track_row.id,
                             created_at: chrono::Utc::now(),
-                            lap_time_ms: sim_state.graphics.lap_timing.best.millis as i64,
+                            lap_time_ms,
                             car_id: car_row.id,
+                            lap_id,
+                            surface: conditions.surface,
+                            bop_era: bop_era.clone(),
                         };
 
-                        sqlx::query_as!(
-                            BestLap,
-                "INSERT INTO best_lap (driver_id, track_id, car_id, created_at, lap_time_ms) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (driver_id, track_id, car_id) DO UPDATE set lap_time_ms=$5 RETURNING *",
-                &new_best_time.driver_id,
-                &new_best_time.track_id,
-                &new_best_time.car_id,
-                &new_best_time.created_at,
-                &new_best_time.lap_time_ms
-            ).fetch_one(&pool).await?;
-
-                        let faster_by = best_laps
-                            .car
-                            .mine
-                            .clone()
-                            .map(|t| {
-                                format!(
-                                    " (-{}ms)",
-                                    pad_lap_segment(
-                                        (t.lap_time_ms - new_best_time.lap_time_ms) as u64,
-                                        3
-                                    )
-                                    .to_string()
-                                )
-                            })
-                            .unwrap_or("".to_string());
+                        // The leaderboard may be narrowed to a temperature band, so only replace a
+                        // surface PB that is actually slower.
+                        storage.save_best_lap(&new_best_time).await?;
 
                         let fastest_for_category = best_laps
                             .category
@@ -264,104 +574,235 @@ async fn main() -> Result<()> {
                             .map(|t| t.lap_time_ms > new_best_time.lap_time_ms)
                             .unwrap_or(false);
 
-                        let message_prefix = if fastest_for_category {
-                            format!("{} fastest", car.category).to_string()
+                        let tier = if fastest_for_category {
+                            PbTier::CategoryFastest
                         } else if fastest_for_car {
-                            "Car fastest".to_string()
+                            PbTier::CarFastest
                         } else if my_fastest_for_category {
-                            format!("{} PB", car.category).to_string()
+                            PbTier::CategoryPb
                         } else {
-                            "Car PB".to_string()
+                            PbTier::CarPb
                         };
 
-                        discord_webhook
-                            .send(&Message::new(|m| {
-                                m.content(format!(
-                                    "{message_prefix} {}{faster_by} in {} on {}",
-                                    format_lap_time(Some(new_best_time.clone())),
-                                    car.name,
-                                    track
-                                ))
-                                .username(format!("{}'s ACC Bot", driver.name))
-                            }))
-                            .await?;
+                        let pb = PersonalBest {
+                            tier,
+                            driver: driver.name.clone(),
+                            car: car.clone(),
+                            category: category.clone(),
+                            track: track.clone(),
+                            conditions: conditions.to_string(),
+                            lap_time_ms: new_best_time.lap_time_ms,
+                            previous_pb_ms: best_laps.car.mine.as_ref().map(|t| t.lap_time_ms),
+                            car_record_ms: best_laps.car.overall.as_ref().map(|t| t.lap_time_ms),
+                            category_record_ms: best_laps
+                                .category
+                                .overall
+                                .as_ref()
+                                .map(|t| t.lap_time_ms),
+                            car_position: best_laps
+                                .car
+                                .position_for(driver.id, new_best_time.lap_time_ms),
+                            category_position: best_laps
+                                .category
+                                .position_for(driver.id, new_best_time.lap_time_ms),
+                        };
+                        if let Err(error) = notifier.personal_best(&pb).await {
+                            dashboard.warn(format!("Couldn't send the notification: {error}"));
+                        }
+                        events.publish(Event::PersonalBest(pb.clone())).await?;
+                        for (kind, previous) in [
+                            (
+                                RecordKind::Category,
+                                best_laps
+                                    .category
+                                    .overall
+                                    .as_ref()
+                                    .filter(|_| fastest_for_category),
+                            ),
+                            (
+                                RecordKind::Car,
+                                best_laps.car.overall.as_ref().filter(|_| fastest_for_car),
+                            ),
+                        ] {
+                            let Some(previous) = previous else {
+                                continue;
+                            };
+                            let record = RecordSet {
+                                driver: driver.name.clone(),
+                                track: track.clone(),
+                                car: car.clone(),
+                                category: category.clone(),
+                                conditions: conditions.to_string(),
+                                lap_time_ms,
+                                previous_driver: previous.driver_name.clone(),
+                                previous_lap_time_ms: previous.lap_time_ms,
+                            };
+                            events
+                                .publish(match kind {
+                                    RecordKind::Car => Event::CarRecord(record),
+                                    RecordKind::Category => Event::CategoryRecord(record),
+                                })
+                                .await?;
+                        }
+
+                        // Only records held by someone else count as beaten, and a category record
+                        // set in this car is the car record too, so it's only announced once.
+                        let beaten_category = best_laps
+                            .category
+                            .overall
+                            .clone()
+                            .filter(|t| fastest_for_category && t.driver_id != driver.id);
+                        let beaten_car = best_laps.car.overall.clone().filter(|t| {
+                            fastest_for_car
+                                && t.driver_id != driver.id
+                                && beaten_category.as_ref().is_none_or(|c| c.id != t.id)
+                        });
+                        for (kind, record) in [
+                            (RecordKind::Category, beaten_category),
+                            (RecordKind::Car, beaten_car),
+                        ] {
+                            let Some(record) = record else {
+                                continue;
+                            };
+                            let beaten_discord_user_id =
+                                match storage.discord_user_id(record.driver_id).await {
+                                    Err(error) if storage::is_unreachable(&error) => None,
+                                    result => result?,
+                                };
+                            if let Err(error) = notifier
+                                .record_beaten(&RecordBeaten {
+                                    kind,
+                                    pb: pb.clone(),
+                                    beaten_driver: record.driver_name,
+                                    beaten_discord_user_id,
+                                    beaten_lap_time_ms: record.lap_time_ms,
+                                    set_at: record.created_at,
+                                })
+                                .await
+                            {
+                                dashboard.warn(format!("Couldn't send the notification: {error}"));
+                            }
+                        }
+
+                        // Kept if the standings can't be refreshed while the database is
+                        // unreachable.
+                        let personal_best = BestLapWithDriver {
+                            id: lap_id,
+                            driver_id: driver.id,
+                            track_id: track_row.id,
+                            created_at: new_best_time.created_at,
+                            lap_time_ms,
+                            driver_name: driver.name.clone(),
+                            car_id: car_row.id,
+                            car_name: car_row.name.clone(),
+                            car_display_name: car_row.display_name.clone(),
+                        };
+                        best_laps.car.insert_personal_best(&personal_best, &sectors);
+                        best_laps
+                            .category
+                            .insert_personal_best(&personal_best, &sectors);
                         refresh = true;
                     }
+                } else {
+                    sector_timer.finish_lap(0);
                 }
             }
 
-            let last_lap = if sim_state.graphics.lap_timing.last.millis < i32::MAX {
-                Some(sim_state.graphics.lap_timing.last.clone())
-            } else {
-                None
-            };
+            lap_validity.update(&state);
+            let sector_completed =
+                sector_timer.update(state.current_sector_index, state.last_sector_ms);
+
+            let last_lap = state.last_lap_ms.map(|lap_time_ms| LastLap {
+                lap_time_ms,
+                invalid_reason: last_lap_invalid_reason,
+            });
 
             if refresh {
-                best_laps = refresh_laps(
-                    &pool,
-                    &driver,
+                best_laps = refresh_laps_or_keep(
+                    storage.as_ref(),
+                    &mut dashboard,
+                    driver,
                     &track_row,
                     &car_row,
-                    last_lap,
-                    false,
-                    printer.by_ref(),
+                    best_laps,
+                    conditions.clone(),
                 )
                 .await?;
             }
+            if refresh || redraw {
+                events
+                    .publish(Event::Standings(Standings {
+                        driver: driver.name.clone(),
+                        track: track.clone(),
+                        car: car.clone(),
+                        category: category.clone(),
+                        conditions: conditions.to_string(),
+                        car_standing: best_laps.car.standing(),
+                        category_standing: best_laps.category.standing(),
+                        last_lap: last_lap.as_ref().map(|lap| events::LastLap {
+                            lap_time_ms: lap.lap_time_ms,
+                            invalid_reason: lap.invalid_reason.map(|r| r.to_string()),
+                        }),
+                    }))
+                    .await?;
+            }
+            if refresh || redraw || sector_completed {
+                dashboard.view.status = format!("{} · {}", state.session_type, state.conditions);
+                dashboard.view.best_laps = best_laps.clone();
+                dashboard.view.last_lap = last_lap;
+                dashboard.view.current_sectors = sector_timer.current().to_vec();
+                dashboard.draw()?;
+            }
+        }
+
+        if let Some(previous) = session_row {
+            storage.end_session(previous.id, chrono::Utc::now()).await?;
+        }
+        if let Some(previous) = session_event {
+            events.publish(Event::SessionEnded(previous)).await?;
         }
+        if quit {
+            return Ok(());
+        }
+    }
+}
+
+/// Warns about a track or car the tracker doesn't recognise until an admin has named it, as their
+/// laps are shown under the game's name for them.
+fn unrecognised_warning(catalogue: &Catalogue, track: &TrackRow, car: &CarRow) -> Option<String> {
+    let mut unrecognised = Vec::new();
+    if track.display_name.is_none() && catalogue.track(&track.name).is_none() {
+        unrecognised.push(format!("track {}", track.name));
+    }
+    if car.category == CarCategory::UNKNOWN.to_string()
+        || (car.display_name.is_none() && catalogue.car(&car.name).is_none())
+    {
+        unrecognised.push(format!("car {}", car.name));
     }
+    let named = match unrecognised.len() {
+        0 => return None,
+        1 => "it's",
+        _ => "they're",
+    };
+    Some(format!(
+        "Unrecognised {}, laps are still tracked until {named} named",
+        unrecognised.join(" and ")
+    ))
 }
 
 async fn refresh_laps(
-    pool: &Pool<Postgres>,
+    storage: &dyn Storage,
     driver: &Driver,
     track: &TrackRow,
     car: &CarRow,
-    last_lap: Option<Time>,
-    is_init: bool,
-    printer: &mut Stdout,
+    conditions: ConditionsFilter,
 ) -> Result<BestLaps> {
-    let car_records = sqlx::query_as::<_, BestLapWithDriver>(
-        r#"SELECT best_lap.id,
-       best_lap.track_id,
-       best_lap.driver_id,
-       best_lap.lap_time_ms,
-       best_lap.created_at,
-       best_lap.car_id,
-       d."name" as driver_name,
-       c.name     as car_name,
-       c.category as car_category
-       from best_lap
-         INNER JOIN public.driver d on d.id = best_lap.driver_id
-         INNER JOIN public.car c on c.id = best_lap.car_id
-       WHERE track_id = $1 AND c.id = $2
-       ORDER BY lap_time_ms ASC"#,
-    )
-    .bind(track.id)
-    .bind(car.id)
-    .fetch_all(pool)
-    .await?;
-
-    let category_records = sqlx::query_as::<_, BestLapWithDriver>(
-        r#"SELECT best_lap.id,
-       best_lap.track_id,
-       best_lap.driver_id,
-       best_lap.lap_time_ms,
-       best_lap.created_at,
-       best_lap.car_id,
-       d."name" as driver_name,
-       c.name     as car_name,
-       c.category as car_category
-       from best_lap
-         INNER JOIN public.driver d on d.id = best_lap.driver_id
-         INNER JOIN public.car c on c.id = best_lap.car_id
-       WHERE track_id = $1 AND c.category = $2
-       ORDER BY lap_time_ms ASC"#,
-    )
-    .bind(track.id)
-    .bind(car.category.to_string())
-    .fetch_all(pool)
-    .await?;
+    let car_records = storage
+        .leaderboard(track.id, Some(&[car.id]), None, &conditions)
+        .await?;
+    let category_records = storage
+        .leaderboard(track.id, None, Some(&car.category.to_string()), &conditions)
+        .await?;
 
     let my_best_lap_for_car = car_records
         .iter()
@@ -375,18 +816,62 @@ async fn refresh_laps(
     let best_overall_for_car = car_records.first().cloned();
     let best_overall_for_category = category_records.first().cloned();
 
-    let result = BestLaps {
+    let theoretical_best = storage
+        .theoretical_best(driver.id, track.id, car.id, &conditions)
+        .await?;
+
+    Ok(BestLaps {
+        conditions,
         car: MyLapAndBestLap {
+            overall_sectors: fetch_sectors(storage, &best_overall_for_car).await?,
             mine: my_best_lap_for_car,
             overall: best_overall_for_car,
+            records: car_records,
         },
         category: MyLapAndBestLap {
+            overall_sectors: fetch_sectors(storage, &best_overall_for_category).await?,
             mine: my_best_lap_for_category,
             overall: best_overall_for_category,
+            records: category_records,
         },
-    };
-    log_laps(result.clone(), last_lap, !is_init, printer)?;
-    Ok(result.clone())
+        theoretical_best,
+    })
+}
+
+/// Like [`refresh_laps`], but warns when the database can't be reached, as laps are queued until
+/// it's back. The `current` standings are kept if they're for the same conditions, and otherwise
+/// there's nothing to compare laps against until it's back.
+async fn refresh_laps_or_keep(
+    storage: &dyn Storage,
+    dashboard: &mut Dashboard,
+    driver: &Driver,
+    track: &TrackRow,
+    car: &CarRow,
+    current: BestLaps,
+    conditions: ConditionsFilter,
+) -> Result<BestLaps> {
+    match refresh_laps(storage, driver, track, car, conditions.clone()).await {
+        Ok(best_laps) => Ok(best_laps),
+        Err(error) if storage::is_unreachable(&error) => {
+            dashboard.warn("Can't reach the database, laps are queued until it's back".to_string());
+            if current.conditions == conditions {
+                Ok(current)
+            } else {
+                Ok(BestLaps {
+                    conditions,
+                    ..BestLaps::default()
+                })
+            }
+        }
+        Err(error) => Err(error),
+    }
+}
+
+async fn fetch_sectors(storage: &dyn Storage, lap: &Option<BestLapWithDriver>) -> Result<Vec<i64>> {
+    match lap {
+        Some(lap) => storage.sectors(lap.id).await,
+        None => Ok(Vec::new()),
+    }
 }
 
 fn pad_lap_segment(segment: u64, length: usize) -> String {
@@ -398,7 +883,7 @@ fn pad_lap_segment(segment: u64, length: usize) -> String {
         for _ in 0..(length - input.len()) {
             padded.push('0');
         }
-        padded.push_str(&*input);
+        padded.push_str(&input);
         padded
     }
 }
@@ -417,171 +902,89 @@ fn format_lap_time<T: LapTime>(lap_time: Option<T>) -> String {
         .unwrap_or("None".to_string())
 }
 
-fn log_laps(
-    laps: BestLaps,
-    last_lap: Option<Time>,
-    refresh: bool,
-    printer: &mut Stdout,
-) -> Result<()> {
-    let is_my_lap_for_car_fastest = laps.car.mine.clone().is_some_and(|mine| {
-        laps.car
-            .overall
-            .clone()
-            .is_some_and(|overall| mine.id == overall.id)
-    });
-
-    if refresh {
-        printer.execute(MoveToPreviousLine(3))?;
-    }
-
-    if is_my_lap_for_car_fastest {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Green))?
-            .execute(Print(pad_string(format!(
-                "You are fastest in this car: {}",
-                format_lap_time(laps.clone().car.mine),
-            ))))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    } else if laps.car.clone().mine.is_some() {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Blue))?
-            .execute(Print(pad_string(format!(
-                "Car PB: {} Best: {} Diff: {}ms",
-                format_lap_time(laps.car.mine.clone()),
-                format_lap_time(laps.car.overall.clone()),
-                laps.car
-                    .mine
-                    .clone()
-                    .map(|t| {
-                        (t.lap_time_ms
-                            - laps.car.overall.clone().map(|t| t.lap_time_ms).unwrap_or(0))
-                        .to_string()
-                    })
-                    .unwrap_or("".to_string())
-            ))))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    } else if laps.car.overall.clone().is_some() {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Red))?
-            .execute(Print(pad_string(format!(
-                "No PB in this car. Best: {}",
-                format_lap_time(laps.car.overall.clone()),
-            ))))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    } else {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Magenta))?
-            .execute(Print(pad_string(
-                "There are no lap times in this car".to_string(),
-            )))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::NoopNotifier;
+    use crate::storage::SqliteStorage;
+    use sqlx::SqlitePool;
+
+    #[tokio::test]
+    async fn replaying_a_recording_stores_its_laps_and_personal_bests() -> Result<()> {
+        let path = env::temp_dir().join(format!("sim_leaderboards_{}.db", std::process::id()));
+        let url = format!("sqlite:{}", path.display());
+        let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::connect(&url).await?);
+        let catalogue = Arc::new(Catalogue::load(None)?);
+        let driver = storage.upsert_driver("Alice").await?;
+        let mut source = Replay::open(
+            Path::new(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/spa_bmw_m4_gt3.jsonl"
+            )),
+            0.0,
+        )?;
+
+        track_laps(
+            storage.clone(),
+            catalogue.clone(),
+            &mut source,
+            &driver,
+            None,
+            Dashboard::headless(driver.id, 5, catalogue),
+            &NoopNotifier,
+        )
+        .await?;
 
-    let is_my_lap_for_category_fastest = laps.category.clone().mine.is_some_and(|mine| {
-        laps.category
-            .overall
-            .clone()
-            .is_some_and(|overall| mine.id == overall.id)
-    });
-
-    if is_my_lap_for_category_fastest {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Green))?
-            .execute(Print(pad_string(format!(
-                "You are fastest in this category {}",
-                format_lap_time(laps.category.mine.clone())
-            ))))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    } else if laps.category.clone().mine.is_some() {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Blue))?
-            .execute(Print(pad_string(format!(
-                "Category PB: {} Best: {} Diff: {}ms",
-                format_lap_time(laps.category.mine.clone()),
-                format_lap_time(laps.category.overall.clone()),
-                laps.category
-                    .mine
-                    .clone()
-                    .map(|t| {
-                        (t.lap_time_ms
-                            - laps
-                                .category
-                                .overall
-                                .clone()
-                                .map(|t| t.lap_time_ms)
-                                .unwrap_or(0))
-                        .to_string()
-                    })
-                    .unwrap_or("".to_string())
-            ))))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    } else if laps.category.overall.clone().is_some() {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Red))?
-            .execute(Print(pad_string(format!(
-                "No PB in this category. Best: {}",
-                format_lap_time(laps.category.overall.clone()),
-            ))))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    } else {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::Magenta))?
-            .execute(Print(pad_string(
-                "No lap times in this category".to_string(),
-            )))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    }
+        let pool = SqlitePool::connect(&url).await?;
+        let laps: Vec<(i32, i64, bool, Option<String>, String)> = sqlx::query_as(
+            "SELECT lap_number, lap_time_ms, is_valid, invalid_reason, surface FROM lap ORDER BY lap_number",
+        )
+        .fetch_all(&pool)
+        .await?;
+        let lap = |lap_number, lap_time_ms, invalid_reason: Option<&str>, surface: &str| {
+            (
+                lap_number,
+                lap_time_ms,
+                invalid_reason.is_none(),
+                invalid_reason.map(str::to_string),
+                surface.to_string(),
+            )
+        };
+        assert_eq!(
+            laps,
+            vec![
+                lap(1, 135000, None, "DRY"),
+                lap(2, 132000, None, "DRY"),
+                // The fastest lap cut the track, so it can't be a personal best.
+                lap(3, 129000, Some("track limits"), "DRY"),
+                lap(4, 130500, None, "DRY"),
+                lap(5, 150000, None, "WET"),
+            ]
+        );
+
+        let best_laps: Vec<(i64, String, String)> =
+            sqlx::query_as("SELECT lap_time_ms, surface, bop_era FROM best_lap ORDER BY surface")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(
+            best_laps,
+            vec![
+                (130500, "DRY".to_string(), "2024".to_string()),
+                (150000, "WET".to_string(), "2024".to_string()),
+            ]
+        );
+
+        let sectors: Vec<i64> = sqlx::query_scalar(
+            "SELECT s.sector_time_ms FROM lap_sector s JOIN lap l ON l.id = s.lap_id WHERE l.lap_number = 4 ORDER BY s.sector_index",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(sectors, vec![38500, 48500, 43500]);
 
-    if last_lap.is_some() {
-        let last_lap_ms = last_lap.clone().unwrap().millis as i64;
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::White))?
-            .execute(Print(pad_string(
-                format!(
-                    "Last: {} Car diff: {}ms Category diff {}ms",
-                    last_lap.unwrap().text,
-                    laps.car
-                        .overall
-                        .clone()
-                        .map(|t| (last_lap_ms - t.lap_time_ms).to_string())
-                        .unwrap_or("∞".to_string()),
-                    laps.category
-                        .clone()
-                        .overall
-                        .clone()
-                        .map(|t| (last_lap_ms - t.lap_time_ms).to_string())
-                        .unwrap_or("∞".to_string())
-                )
-                .to_string(),
-            )))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
-    } else {
-        printer
-            .execute(MoveToNextLine(1))?
-            .execute(SetForegroundColor(Color::White))?
-            .execute(Print(pad_string(
-                "Stop staring at me & start driving".to_string(),
-            )))?
-            .execute(ResetColor)?
-            .execute(SetAttribute(Attribute::Reset))?;
+        pool.close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+        Ok(())
     }
-    Ok(())
 }
@@ -0,0 +1,122 @@
+use crate::format_lap_time;
+use crate::notifier::{Notifier, PbTier, PersonalBest, RecordBeaten};
+use anyhow::{Context, Result};
+use discord_webhook2::message::Message;
+use discord_webhook2::webhook::DiscordWebhook;
+
+/// Posts to a Discord channel through an incoming webhook.
+pub(crate) struct DiscordNotifier {
+    webhook: DiscordWebhook,
+}
+
+impl DiscordNotifier {
+    pub(crate) fn new(url: &str) -> Result<DiscordNotifier> {
+        Ok(DiscordNotifier {
+            webhook: DiscordWebhook::new(url).context("Invalid DISCORD_WEBHOOK")?,
+        })
+    }
+}
+
+fn tier_colour(tier: PbTier) -> u32 {
+    match tier {
+        PbTier::CategoryFastest => 0x9b59b6,
+        PbTier::CarFastest => 0x2ecc71,
+        PbTier::CategoryPb => 0x3498db,
+        PbTier::CarPb => 0x95a5a6,
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for DiscordNotifier {
+    async fn personal_best(&self, pb: &PersonalBest) -> Result<()> {
+        let fields = [
+            ("Car", pb.car.clone()),
+            ("Category", pb.category.clone()),
+            ("Track", pb.track.clone()),
+            ("Time", format_lap_time(Some(pb.lap_time_ms))),
+            (
+                "vs previous PB",
+                pb.delta_to(pb.previous_pb_ms)
+                    .unwrap_or("No previous PB".to_string()),
+            ),
+            ("Conditions", pb.conditions.clone()),
+            (
+                "vs car record",
+                pb.delta_to(pb.car_record_ms)
+                    .unwrap_or("No record".to_string()),
+            ),
+            (
+                "vs category record",
+                pb.delta_to(pb.category_record_ms)
+                    .unwrap_or("No record".to_string()),
+            ),
+            (
+                "Position",
+                format!(
+                    "{} in car, {} in {}",
+                    pb.car_position, pb.category_position, pb.category
+                ),
+            ),
+        ];
+        self.webhook
+            .send(&Message::new(|m| {
+                m.username(format!("{}'s ACC Bot", pb.driver)).embed(|e| {
+                    fields.into_iter().fold(
+                        e.title(pb.headline())
+                            .description(format!(
+                                "{} set a {} in the {} at {}",
+                                pb.driver,
+                                format_lap_time(Some(pb.lap_time_ms)),
+                                pb.car,
+                                pb.track
+                            ))
+                            .color(tier_colour(pb.tier)),
+                        |e, (name, value)| e.field(|f| f.name(name).value(value).inline(true)),
+                    )
+                })
+            }))
+            .await?;
+        Ok(())
+    }
+
+    async fn record_beaten(&self, beaten: &RecordBeaten) -> Result<()> {
+        let pb = &beaten.pb;
+        self.webhook
+            .send(&Message::new(|m| {
+                let m = m.username(format!("{}'s ACC Bot", pb.driver)).embed(|e| {
+                    e.title(format!("{} record beaten", beaten.record_name()))
+                        .description(beaten.summary())
+                        .color(tier_colour(pb.tier))
+                        .field(|f| f.name("Beaten").value(&beaten.beaten_driver).inline(true))
+                        .field(|f| {
+                            f.name("Old record")
+                                .value(format_lap_time(Some(beaten.beaten_lap_time_ms)))
+                                .inline(true)
+                        })
+                        .field(|f| {
+                            f.name("New record")
+                                .value(format_lap_time(Some(pb.lap_time_ms)))
+                                .inline(true)
+                        })
+                        .field(|f| {
+                            f.name("Margin")
+                                .value(format!("{}ms", beaten.margin_ms()))
+                                .inline(true)
+                        })
+                        .field(|f| f.name("Stood for").value(beaten.stood_for()).inline(true))
+                        .field(|f| f.name("Conditions").value(&pb.conditions).inline(true))
+                });
+                match &beaten.beaten_discord_user_id {
+                    Some(id) => m.content(format!(
+                        "<@{id}> {} took your {} record at {}",
+                        pb.driver,
+                        beaten.record_name(),
+                        pb.track
+                    )),
+                    None => m,
+                }
+            }))
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+mod r#discord;
+mod r#stdout;
+mod r#webhook;
+
+use crate::dashboard::Announcer;
+use crate::{format_lap_time, pad_lap_segment};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use serde::Serialize;
+use std::env;
+use std::fmt;
+
+pub(crate) use r#discord::DiscordNotifier;
+pub(crate) use r#stdout::{NoopNotifier, StdoutNotifier};
+pub(crate) use r#webhook::WebhookNotifier;
+
+/// How notable a new personal best is, from most to least.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PbTier {
+    CategoryFastest,
+    CarFastest,
+    CategoryPb,
+    CarPb,
+}
+
+/// A new personal best worth telling people about.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PersonalBest {
+    pub(crate) tier: PbTier,
+    pub(crate) driver: String,
+    pub(crate) car: String,
+    pub(crate) category: String,
+    pub(crate) track: String,
+    pub(crate) conditions: String,
+    pub(crate) lap_time_ms: i64,
+    /// The driver's previous best in this car, if they had one.
+    pub(crate) previous_pb_ms: Option<i64>,
+    /// The records this lap was up against, which may have been the driver's own.
+    pub(crate) car_record_ms: Option<i64>,
+    pub(crate) category_record_ms: Option<i64>,
+    pub(crate) car_position: Position,
+    pub(crate) category_position: Position,
+}
+
+/// A place on a leaderboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) struct Position {
+    pub(crate) position: usize,
+    pub(crate) entries: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "P{} of {}", self.position, self.entries)
+    }
+}
+
+impl PersonalBest {
+    pub(crate) fn headline(&self) -> String {
+        match self.tier {
+            PbTier::CategoryFastest => format!("{} fastest", self.category),
+            PbTier::CarFastest => "Car fastest".to_string(),
+            PbTier::CategoryPb => format!("{} PB", self.category),
+            PbTier::CarPb => "Car PB".to_string(),
+        }
+    }
+
+    /// How far this lap is from `other`, e.g. `-1500ms`.
+    pub(crate) fn delta_to(&self, other: Option<i64>) -> Option<String> {
+        other.map(|other| format!("{:+}ms", self.lap_time_ms - other))
+    }
+
+    /// A one line summary, e.g. `GT3 PB 2:10:500 (-1500ms) in BMW M4 GT3 on Spa (Dry)`.
+    pub(crate) fn summary(&self) -> String {
+        let faster_by = self
+            .previous_pb_ms
+            .map(|previous| {
+                format!(
+                    " (-{}ms)",
+                    pad_lap_segment((previous - self.lap_time_ms) as u64, 3)
+                )
+            })
+            .unwrap_or_default();
+        format!(
+            "{} {}{} in {} on {} ({})",
+            self.headline(),
+            format_lap_time(Some(self.lap_time_ms)),
+            faster_by,
+            self.car,
+            self.track,
+            self.conditions
+        )
+    }
+}
+
+/// Which kind of record a lap took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RecordKind {
+    Car,
+    Category,
+}
+
+/// Another driver's record that a new personal best has just taken.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RecordBeaten {
+    pub(crate) kind: RecordKind,
+    pub(crate) pb: PersonalBest,
+    pub(crate) beaten_driver: String,
+    /// Set when the beaten driver has a Discord account to mention.
+    pub(crate) beaten_discord_user_id: Option<String>,
+    pub(crate) beaten_lap_time_ms: i64,
+    /// When the beaten record was set.
+    pub(crate) set_at: DateTime<Utc>,
+}
+
+impl RecordBeaten {
+    pub(crate) fn record_name(&self) -> &str {
+        match self.kind {
+            RecordKind::Car => &self.pb.car,
+            RecordKind::Category => &self.pb.category,
+        }
+    }
+
+    pub(crate) fn margin_ms(&self) -> i64 {
+        self.beaten_lap_time_ms - self.pb.lap_time_ms
+    }
+
+    /// How long the record stood, e.g. `3 days`.
+    pub(crate) fn stood_for(&self) -> String {
+        HumanTime::from(Utc::now() - self.set_at).to_text_en(Accuracy::Rough, Tense::Present)
+    }
+
+    /// A one line summary, e.g.
+    /// `Alice took Bob's GT3 record at Spa (Dry) by 412ms with a 2:17:588, it stood for 3 days`.
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "{} took {}'s {} record at {} ({}) by {}ms with a {}, it stood for {}",
+            self.pb.driver,
+            self.beaten_driver,
+            self.record_name(),
+            self.pb.track,
+            self.pb.conditions,
+            self.margin_ms(),
+            format_lap_time(Some(self.pb.lap_time_ms)),
+            self.stood_for()
+        )
+    }
+}
+
+/// Somewhere personal bests get announced.
+#[async_trait::async_trait]
+pub(crate) trait Notifier {
+    async fn personal_best(&self, pb: &PersonalBest) -> Result<()>;
+
+    async fn record_beaten(&self, beaten: &RecordBeaten) -> Result<()>;
+}
+
+/// Picks a notifier from `NOTIFIER` (`discord`, `webhook`, `stdout` or `none`). When it isn't set,
+/// Discord is used if `DISCORD_WEBHOOK` is set and nothing is announced otherwise. `stdout` shows
+/// announcements through `announcer`.
+pub(crate) fn from_env(announcer: Announcer) -> Result<Box<dyn Notifier + Send + Sync>> {
+    let discord_webhook = non_empty_var("DISCORD_WEBHOOK");
+    let kind = match non_empty_var("NOTIFIER") {
+        Some(kind) => kind.to_lowercase(),
+        None if discord_webhook.is_some() => "discord".to_string(),
+        None => "none".to_string(),
+    };
+    Ok(match kind.as_str() {
+        "discord" => Box::new(DiscordNotifier::new(
+            &discord_webhook.context("DISCORD_WEBHOOK is not set")?,
+        )?),
+        "webhook" => Box::new(WebhookNotifier::new(
+            &non_empty_var("NOTIFIER_WEBHOOK_URL").context("NOTIFIER_WEBHOOK_URL is not set")?,
+        )?),
+        "stdout" => Box::new(StdoutNotifier::new(announcer)),
+        "none" => Box::new(NoopNotifier),
+        other => bail!("Unknown NOTIFIER {other}, expected discord, webhook, stdout or none"),
+    })
+}
+
+fn non_empty_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
@@ -0,0 +1,41 @@
+use crate::dashboard::Announcer;
+use crate::notifier::{Notifier, PersonalBest, RecordBeaten};
+use anyhow::Result;
+
+/// Shows announcements on the dashboard, or prints them when it isn't drawn.
+pub(crate) struct StdoutNotifier {
+    announcer: Announcer,
+}
+
+impl StdoutNotifier {
+    pub(crate) fn new(announcer: Announcer) -> StdoutNotifier {
+        StdoutNotifier { announcer }
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for StdoutNotifier {
+    async fn personal_best(&self, pb: &PersonalBest) -> Result<()> {
+        self.announcer.announce(pb.summary());
+        Ok(())
+    }
+
+    async fn record_beaten(&self, beaten: &RecordBeaten) -> Result<()> {
+        self.announcer.announce(beaten.summary());
+        Ok(())
+    }
+}
+
+/// Keeps personal bests to itself.
+pub(crate) struct NoopNotifier;
+
+#[async_trait::async_trait]
+impl Notifier for NoopNotifier {
+    async fn personal_best(&self, _pb: &PersonalBest) -> Result<()> {
+        Ok(())
+    }
+
+    async fn record_beaten(&self, _beaten: &RecordBeaten) -> Result<()> {
+        Ok(())
+    }
+}
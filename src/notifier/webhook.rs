@@ -0,0 +1,65 @@
+use crate::notifier::{Notifier, PersonalBest, RecordBeaten};
+use anyhow::{Context, Result};
+use reqwest::{Client, Url};
+use serde::Serialize;
+
+/// POSTs each announcement as JSON to an arbitrary URL.
+pub(crate) struct WebhookNotifier {
+    client: Client,
+    url: Url,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum WebhookEvent<'a> {
+    PersonalBest {
+        summary: String,
+        #[serde(flatten)]
+        pb: &'a PersonalBest,
+    },
+    RecordBeaten {
+        summary: String,
+        margin_ms: i64,
+        #[serde(flatten)]
+        beaten: &'a RecordBeaten,
+    },
+}
+
+impl WebhookNotifier {
+    pub(crate) fn new(url: &str) -> Result<WebhookNotifier> {
+        Ok(WebhookNotifier {
+            client: Client::new(),
+            url: Url::parse(url).context("Invalid NOTIFIER_WEBHOOK_URL")?,
+        })
+    }
+
+    async fn send(&self, event: &WebhookEvent<'_>) -> Result<()> {
+        self.client
+            .post(self.url.clone())
+            .json(event)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for WebhookNotifier {
+    async fn personal_best(&self, pb: &PersonalBest) -> Result<()> {
+        self.send(&WebhookEvent::PersonalBest {
+            summary: pb.summary(),
+            pb,
+        })
+        .await
+    }
+
+    async fn record_beaten(&self, beaten: &RecordBeaten) -> Result<()> {
+        self.send(&WebhookEvent::RecordBeaten {
+            summary: beaten.summary(),
+            margin_ms: beaten.margin_ms(),
+            beaten,
+        })
+        .await
+    }
+}
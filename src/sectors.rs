@@ -0,0 +1,104 @@
+/// Collects the sector splits of the lap in progress.
+///
+/// The sim only reports the index of the sector the car is in and the time of the sector it just
+/// left, so splits are recorded whenever the sector index moves on. The final sector is derived
+/// from the lap time once the lap is completed.
+#[derive(Debug, Clone)]
+pub(crate) struct SectorTimer {
+    sector_count: usize,
+    current_sector: i32,
+    sectors: Vec<i64>,
+}
+
+impl SectorTimer {
+    pub(crate) fn new(sector_count: i32) -> SectorTimer {
+        SectorTimer {
+            sector_count: sector_count.max(1) as usize,
+            current_sector: 0,
+            sectors: Vec::new(),
+        }
+    }
+
+    /// Returns true when this update completed a sector.
+    pub(crate) fn update(&mut self, sector_index: i32, last_sector_ms: i32) -> bool {
+        if sector_index == self.current_sector {
+            return false;
+        }
+        self.current_sector = sector_index;
+        if sector_index > 0 && last_sector_ms > 0 && self.sectors.len() < self.sector_count - 1 {
+            self.sectors.push(last_sector_ms as i64);
+            return true;
+        }
+        false
+    }
+
+    /// Closes the current lap and returns its sector times, if every sector was seen.
+    pub(crate) fn finish_lap(&mut self, lap_time_ms: i64) -> Option<Vec<i64>> {
+        let mut sectors = std::mem::take(&mut self.sectors);
+        let elapsed: i64 = sectors.iter().sum();
+        if sectors.len() != self.sector_count - 1 || elapsed >= lap_time_ms {
+            return None;
+        }
+        sectors.push(lap_time_ms - elapsed);
+        Some(sectors)
+    }
+
+    pub(crate) fn current(&self) -> &[i64] {
+        &self.sectors
+    }
+}
+
+/// Time from the start of the lap to the end of the given number of sectors.
+pub(crate) fn split_time(sectors: &[i64], count: usize) -> Option<i64> {
+    if count == 0 || sectors.len() < count {
+        None
+    } else {
+        Some(sectors[..count].iter().sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sectors_are_split_as_the_sector_index_moves_on() {
+        let mut timer = SectorTimer::new(3);
+        assert!(!timer.update(0, 0));
+        assert!(timer.update(1, 40000));
+        assert!(!timer.update(1, 40000));
+        assert!(timer.update(2, 50000));
+        assert_eq!(timer.current(), [40000, 50000]);
+
+        // The frame completing the lap closes it, then wraps back to the first sector while
+        // reporting the time of the last one, which comes from the lap time instead.
+        assert_eq!(timer.finish_lap(130000), Some(vec![40000, 50000, 40000]));
+        assert!(!timer.update(0, 40123));
+        assert!(timer.current().is_empty());
+
+        assert!(timer.update(1, 39000));
+        assert!(timer.update(2, 49000));
+        assert_eq!(timer.finish_lap(127000), Some(vec![39000, 49000, 39000]));
+    }
+
+    #[test]
+    fn laps_with_splits_that_dont_add_up_have_none() {
+        let mut timer = SectorTimer::new(3);
+        // A sector skipped, e.g. while the game was paused.
+        assert!(timer.update(2, 50000));
+        assert_eq!(timer.finish_lap(130000), None);
+        timer.update(0, 40000);
+
+        assert!(timer.update(1, 40000));
+        assert!(timer.update(2, 50000));
+        assert_eq!(timer.finish_lap(85000), None);
+    }
+
+    #[test]
+    fn split_times_add_up_the_sectors_before() {
+        let sectors = [40000, 50000, 40000];
+        assert_eq!(split_time(&sectors, 0), None);
+        assert_eq!(split_time(&sectors, 2), Some(90000));
+        assert_eq!(split_time(&sectors[..1], 2), None);
+    }
+}
@@ -0,0 +1,240 @@
+use super::{AppState, ConditionsQuery, LatestStandings, LeaderboardQuery, ServerError};
+use crate::catalogue::Catalogue;
+use crate::storage::Storage;
+use crate::{
+    BestLapWithDriver, CarRow, CategoryRecordRow, PersonalBestRow, RecentPersonalBestRow, TrackRow,
+};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+pub(super) fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/tracks", get(tracks))
+        .route("/api/tracks/{track}/leaderboard", get(leaderboard))
+        .route("/api/cars", get(cars))
+        .route("/api/categories", get(categories))
+        .route("/api/drivers/{driver}", get(driver))
+        .route("/api/pbs/recent", get(recent_pbs))
+        .route("/api/events", get(events))
+        .route("/api/standings/{driver}", get(standings))
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let (status, error) = self.into_parts();
+        (status, Json(ErrorBody { error })).into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+type ApiResult<T> = Result<Json<T>, ServerError>;
+
+#[derive(Serialize)]
+struct Track {
+    #[serde(flatten)]
+    track: TrackRow,
+    display_name: String,
+}
+
+async fn tracks(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+) -> ApiResult<Vec<Track>> {
+    Ok(Json(
+        storage
+            .tracks()
+            .await?
+            .into_iter()
+            .map(|track| Track {
+                display_name: catalogue.track_label(
+                    &track.name,
+                    track.display_name.as_deref(),
+                    &track.layout,
+                ),
+                track,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Serialize)]
+struct Car {
+    #[serde(flatten)]
+    car: CarRow,
+    display_name: String,
+    /// The rest are from the catalogue, so missing for cars it doesn't have.
+    manufacturer: Option<String>,
+    family: Option<String>,
+    year: Option<i32>,
+    bop_era: Option<String>,
+}
+
+async fn cars(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+) -> ApiResult<Vec<Car>> {
+    Ok(Json(
+        storage
+            .cars()
+            .await?
+            .into_iter()
+            .map(|car| {
+                let entry = catalogue.car(&car.name);
+                Car {
+                    display_name: catalogue.car_label(&car.name, car.display_name.as_deref()),
+                    manufacturer: entry.map(|entry| entry.manufacturer.clone()),
+                    family: entry.map(|entry| entry.family.clone()),
+                    year: entry.map(|entry| entry.year),
+                    bop_era: entry.map(|entry| entry.bop_era.clone()),
+                    car,
+                }
+            })
+            .collect(),
+    ))
+}
+
+async fn categories(State(storage): State<Arc<dyn Storage>>) -> ApiResult<Vec<String>> {
+    Ok(Json(storage.categories().await?))
+}
+
+#[derive(Serialize)]
+struct LeaderboardEntry {
+    position: usize,
+    #[serde(flatten)]
+    lap: BestLapWithDriver,
+}
+
+async fn leaderboard(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+    Path(track): Path<String>,
+    Query(query): Query<LeaderboardQuery>,
+    Query(conditions): Query<ConditionsQuery>,
+) -> ApiResult<Vec<LeaderboardEntry>> {
+    let filter = query.resolve(storage.as_ref(), &catalogue, &track).await?;
+    let laps = storage
+        .leaderboard(
+            filter.track.id,
+            filter.car_ids.as_deref(),
+            filter.category.as_deref(),
+            &conditions.filter(&catalogue)?,
+        )
+        .await?;
+    Ok(Json(
+        laps.into_iter()
+            .enumerate()
+            .map(|(index, lap)| LeaderboardEntry {
+                position: index + 1,
+                lap,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Serialize)]
+struct DriverProfile {
+    name: String,
+    lap_count: i64,
+    personal_bests: Vec<PersonalBestRow>,
+    /// Category records the driver currently holds.
+    records: Vec<CategoryRecordRow>,
+}
+
+async fn driver(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+    Path(driver): Path<String>,
+    Query(query): Query<ConditionsQuery>,
+) -> ApiResult<DriverProfile> {
+    let lap_count = storage
+        .lap_count(&driver)
+        .await?
+        .ok_or_else(|| ServerError::NotFound(format!("Unknown driver {driver}")))?;
+    let conditions = query.filter(&catalogue)?;
+    let records = storage
+        .records(&conditions)
+        .await?
+        .into_iter()
+        .filter(|record| record.driver_name == driver)
+        .collect();
+    Ok(Json(DriverProfile {
+        personal_bests: storage.personal_bests(&driver, &conditions).await?,
+        name: driver,
+        lap_count,
+        records,
+    }))
+}
+
+#[derive(Deserialize)]
+struct RecentQuery {
+    limit: Option<i64>,
+}
+
+async fn recent_pbs(
+    State(storage): State<Arc<dyn Storage>>,
+    Query(query): Query<RecentQuery>,
+) -> ApiResult<Vec<RecentPersonalBestRow>> {
+    Ok(Json(
+        storage
+            .recent_personal_bests(query.limit.unwrap_or(20))
+            .await?,
+    ))
+}
+
+async fn events(
+    upgrade: WebSocketUpgrade,
+    State(events): State<broadcast::Sender<String>>,
+) -> Response {
+    let events = events.subscribe();
+    upgrade.on_upgrade(|socket| stream_events(socket, events))
+}
+
+async fn stream_events(mut socket: WebSocket, mut events: broadcast::Receiver<String>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if socket.send(Message::Text(event.into())).await.is_err() {
+                        return;
+                    }
+                }
+                // A client too slow to keep up misses events rather than holding others back.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            },
+            message = socket.recv() => {
+                if !matches!(message, Some(Ok(_))) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// The driver's latest standings while they're in a session, as streamed in `standings` events.
+async fn standings(
+    State(standings): State<LatestStandings>,
+    Path(driver): Path<String>,
+) -> Result<Response, ServerError> {
+    let standings = standings.lock().unwrap().get(&driver).cloned();
+    match standings {
+        Some(standings) => {
+            Ok(([(header::CONTENT_TYPE, "application/json")], standings).into_response())
+        }
+        None => Err(ServerError::NotFound(format!(
+            "{driver} isn't in a session"
+        ))),
+    }
+}
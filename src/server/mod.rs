@@ -0,0 +1,249 @@
+mod r#api;
+mod r#pages;
+
+use crate::catalogue::{CarFilterError, Catalogue};
+use crate::session::{ConditionsFilter, Surface};
+use crate::storage::{EventListener, Storage};
+use crate::TrackRow;
+use anyhow::Result;
+use axum::extract::FromRef;
+use axum::http::StatusCode;
+use axum::Router;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+pub(crate) async fn serve(
+    storage: Arc<dyn Storage>,
+    catalogue: Arc<Catalogue>,
+    address: SocketAddr,
+) -> Result<()> {
+    let events = broadcast::channel(64).0;
+    let standings = LatestStandings::default();
+    tokio::spawn(relay_events(
+        storage.listen_for_events().await?,
+        events.clone(),
+        standings.clone(),
+    ));
+
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    println!("Serving leaderboards on http://{}", listener.local_addr()?);
+    axum::serve(
+        listener,
+        router(AppState {
+            storage,
+            catalogue,
+            events,
+            standings,
+        }),
+    )
+    .await?;
+    Ok(())
+}
+
+fn router(state: AppState) -> Router {
+    Router::new()
+        .merge(api::routes())
+        .merge(pages::routes())
+        .with_state(state)
+}
+
+#[derive(Clone)]
+struct AppState {
+    storage: Arc<dyn Storage>,
+    catalogue: Arc<Catalogue>,
+    /// Events published by trackers, as the JSON they were published as.
+    events: broadcast::Sender<String>,
+    standings: LatestStandings,
+}
+
+/// The latest `standings` event of each driver in a session, keyed by driver name, so overlays
+/// have something to show before the next event arrives.
+type LatestStandings = Arc<Mutex<HashMap<String, String>>>;
+
+impl FromRef<AppState> for Arc<dyn Storage> {
+    fn from_ref(state: &AppState) -> Arc<dyn Storage> {
+        state.storage.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Catalogue> {
+    fn from_ref(state: &AppState) -> Arc<Catalogue> {
+        state.catalogue.clone()
+    }
+}
+
+impl FromRef<AppState> for broadcast::Sender<String> {
+    fn from_ref(state: &AppState) -> broadcast::Sender<String> {
+        state.events.clone()
+    }
+}
+
+impl FromRef<AppState> for LatestStandings {
+    fn from_ref(state: &AppState) -> LatestStandings {
+        state.standings.clone()
+    }
+}
+
+/// Hands events published by trackers to everyone streaming them.
+async fn relay_events(
+    mut listener: Box<dyn EventListener>,
+    events: broadcast::Sender<String>,
+    standings: LatestStandings,
+) {
+    loop {
+        let payload = match listener.recv().await {
+            Ok(payload) => payload,
+            Err(error) => {
+                eprintln!("Stopped relaying events: {error}");
+                return;
+            }
+        };
+        if let Ok(EventHeader { event, driver }) = serde_json::from_str(&payload) {
+            let mut standings = standings.lock().unwrap();
+            match event.as_str() {
+                "standings" => drop(standings.insert(driver, payload.clone())),
+                "session_ended" => drop(standings.remove(&driver)),
+                _ => {}
+            }
+        }
+        // Nobody streaming events isn't an error.
+        let _ = events.send(payload);
+    }
+}
+
+/// The fields every event shares.
+#[derive(Deserialize)]
+struct EventHeader {
+    event: String,
+    driver: String,
+}
+
+/// Anything a handler can fail with. The API and the pages render it in their own way.
+enum ServerError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl<E: Into<anyhow::Error>> From<E> for ServerError {
+    fn from(error: E) -> ServerError {
+        ServerError::Internal(error.into())
+    }
+}
+
+impl ServerError {
+    fn into_parts(self) -> (StatusCode, String) {
+        match self {
+            ServerError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ServerError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ServerError::Internal(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+        }
+    }
+}
+
+/// Query parameters narrowing laps down to the conditions they were set in. Dry laps are shown
+/// unless `wet` is set.
+#[derive(Deserialize)]
+struct ConditionsQuery {
+    #[serde(default)]
+    wet: bool,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    min_track_temperature: Option<f32>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    max_track_temperature: Option<f32>,
+    /// The current era if missing, or every era for `all`.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    bop_era: Option<String>,
+}
+
+impl ConditionsQuery {
+    fn filter(&self, catalogue: &Catalogue) -> Result<ConditionsFilter, ServerError> {
+        Ok(ConditionsFilter {
+            surface: if self.wet { Surface::Wet } else { Surface::Dry },
+            track_temperature: match (self.min_track_temperature, self.max_track_temperature) {
+                (None, None) => None,
+                (low, high) => Some((low.unwrap_or(f32::MIN), high.unwrap_or(f32::MAX))),
+            },
+            bop_era: catalogue
+                .bop_era_filter(self.bop_era.as_deref())
+                .map_err(ServerError::BadRequest)?,
+        })
+    }
+}
+
+/// Which laps of a track's leaderboard to rank.
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    /// The track's latest layout if missing.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    layout: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    car: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    category: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    manufacturer: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    family: Option<String>,
+}
+
+/// A leaderboard query resolved against the database.
+struct LeaderboardFilter {
+    track: TrackRow,
+    car_ids: Option<Vec<i64>>,
+    category: Option<String>,
+}
+
+impl LeaderboardQuery {
+    async fn resolve(
+        &self,
+        storage: &dyn Storage,
+        catalogue: &Catalogue,
+        track: &str,
+    ) -> Result<LeaderboardFilter, ServerError> {
+        let track = storage
+            .find_track(track, self.layout.as_deref())
+            .await?
+            .ok_or_else(|| {
+                ServerError::NotFound(format!("No laps have been recorded at {track}"))
+            })?;
+        let filter = catalogue
+            .car_filter(
+                &storage.cars().await?,
+                self.car.as_deref(),
+                self.category.as_deref(),
+                self.manufacturer.as_deref(),
+                self.family.as_deref(),
+            )
+            .map_err(|error| match error {
+                CarFilterError::Unknown(message) => ServerError::NotFound(message),
+                CarFilterError::Invalid(message) => ServerError::BadRequest(message),
+            })?;
+        Ok(LeaderboardFilter {
+            track,
+            car_ids: filter.car_ids,
+            category: filter.category,
+        })
+    }
+}
+
+/// HTML forms submit fields they leave blank as empty strings, which are treated as missing.
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Option::<String>::deserialize(deserializer)?
+        .as_deref()
+        .map(str::trim)
+    {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
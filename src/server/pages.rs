@@ -0,0 +1,463 @@
+use super::{AppState, ConditionsQuery, LeaderboardQuery, ServerError};
+use crate::catalogue::{Catalogue, LEGACY_BOP_ERA};
+use crate::session::ConditionsFilter;
+use crate::storage::Storage;
+use crate::{format_lap_time, RecordSetRow};
+use axum::extract::{Path, Query, State};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::sync::Arc;
+
+pub(super) fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(index))
+        .route("/tracks/{track}", get(track))
+        .route("/drivers/{driver}", get(driver))
+        .route("/records", get(records))
+        .route("/overlay", get(overlay))
+}
+
+/// Renders a [ServerError] as a page rather than JSON.
+struct PageError(ServerError);
+
+impl<E: Into<ServerError>> From<E> for PageError {
+    fn from(error: E) -> PageError {
+        PageError(error.into())
+    }
+}
+
+impl IntoResponse for PageError {
+    fn into_response(self) -> Response {
+        let (status, message) = self.0.into_parts();
+        let title = status.canonical_reason().unwrap_or("Error");
+        (status, page(title, format!("<p>{}</p>", escape(&message)))).into_response()
+    }
+}
+
+type PageResult = Result<Html<String>, PageError>;
+
+async fn index(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+) -> PageResult {
+    let tracks = storage.tracks().await?;
+    let mut body = String::from("<h2>Tracks</h2>");
+    if tracks.is_empty() {
+        body.push_str("<p>No laps have been recorded yet.</p>");
+    } else {
+        body.push_str("<ul>");
+        for track in tracks {
+            write!(
+                body,
+                "<li>{}</li>",
+                track_link(
+                    &catalogue,
+                    &track.name,
+                    track.display_name.as_deref(),
+                    &track.layout
+                )
+            )?;
+        }
+        body.push_str("</ul>");
+    }
+    body.push_str(r#"<h2>Recent records <a class="more" href="/records">all</a></h2>"#);
+    body.push_str(&records_table(
+        &catalogue,
+        &storage.recent_records(10).await?,
+    ));
+    Ok(page("Leaderboards", body))
+}
+
+async fn track(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+    Path(track): Path<String>,
+    Query(query): Query<LeaderboardQuery>,
+    Query(conditions): Query<ConditionsQuery>,
+) -> PageResult {
+    let filter = query.resolve(storage.as_ref(), &catalogue, &track).await?;
+    let conditions_filter = conditions.filter(&catalogue)?;
+    let laps = storage
+        .leaderboard(
+            filter.track.id,
+            filter.car_ids.as_deref(),
+            filter.category.as_deref(),
+            &conditions_filter,
+        )
+        .await?;
+    let cars = storage.cars_at_track(filter.track.id).await?;
+    let mut categories: Vec<&str> = cars.iter().map(|car| car.category.as_str()).collect();
+    categories.dedup();
+    let entries: Vec<_> = cars
+        .iter()
+        .filter_map(|car| catalogue.car(&car.name))
+        .collect();
+    let manufacturers: BTreeSet<&str> = entries
+        .iter()
+        .map(|entry| entry.manufacturer.as_str())
+        .collect();
+    let families: BTreeSet<&str> = entries.iter().map(|entry| entry.family.as_str()).collect();
+
+    let mut body = String::new();
+    if let (Some(length_km), Some(country)) = (filter.track.length_km, &filter.track.country) {
+        write!(body, "<p>{length_km} km · {}", escape(country))?;
+        if let Some(sector_count) = filter.track.sector_count {
+            write!(body, " · {sector_count} sectors")?;
+        }
+        body.push_str("</p>");
+    }
+    body.push_str(r#"<form method="get">"#);
+    if let Some(layout) = &query.layout {
+        write!(
+            body,
+            r#"<input type="hidden" name="layout" value="{}">"#,
+            escape(layout)
+        )?;
+    }
+    body.push_str(r#"<label>Car <select name="car"><option value="">Any</option>"#);
+    for car in &cars {
+        write!(
+            body,
+            r#"<option value="{}"{}>{}</option>"#,
+            escape(&car.name),
+            selected(query.car.as_deref() == Some(&car.name)),
+            escape(&catalogue.car_label(&car.name, car.display_name.as_deref()))
+        )?;
+    }
+    body.push_str(r#"</select></label> <label>Category <select name="category"><option value="">Any</option>"#);
+    for category in categories {
+        write!(
+            body,
+            r#"<option{}>{}</option>"#,
+            selected(filter.category.as_deref() == Some(category)),
+            escape(category)
+        )?;
+    }
+    for (label, name, options, chosen) in [
+        (
+            "Manufacturer",
+            "manufacturer",
+            &manufacturers,
+            &query.manufacturer,
+        ),
+        ("Family", "family", &families, &query.family),
+    ] {
+        write!(
+            body,
+            r#"</select></label> <label>{label} <select name="{name}"><option value="">Any</option>"#
+        )?;
+        for option in options {
+            write!(
+                body,
+                r#"<option{}>{}</option>"#,
+                selected(
+                    chosen
+                        .as_ref()
+                        .is_some_and(|chosen| chosen.eq_ignore_ascii_case(option))
+                ),
+                escape(option)
+            )?;
+        }
+    }
+    body.push_str(r#"</select></label> <label>BoP era <select name="bop_era">"#);
+    for era in catalogue.bop_eras().iter().rev() {
+        write!(
+            body,
+            r#"<option{}>{}</option>"#,
+            selected(conditions_filter.bop_era.as_ref() == Some(&era.id)),
+            escape(&era.id)
+        )?;
+    }
+    write!(
+        body,
+        r#"<option value="{LEGACY_BOP_ERA}"{}>Before eras were recorded</option>"#,
+        selected(conditions_filter.bop_era.as_deref() == Some(LEGACY_BOP_ERA))
+    )?;
+    write!(
+        body,
+        r#"<option value="all"{}>All time</option>"#,
+        selected(conditions_filter.bop_era.is_none())
+    )?;
+    write!(
+        body,
+        r#"</select></label> <label><input type="checkbox" name="wet" value="true"{}> Wet</label>
+<label>Track °C <input type="number" step="any" name="min_track_temperature" value="{}"> to
+<input type="number" step="any" name="max_track_temperature" value="{}"></label>
+<button>Show</button></form>"#,
+        if conditions.wet { " checked" } else { "" },
+        conditions
+            .min_track_temperature
+            .map(|t| t.to_string())
+            .unwrap_or_default(),
+        conditions
+            .max_track_temperature
+            .map(|t| t.to_string())
+            .unwrap_or_default(),
+    )?;
+
+    write!(body, "<p>{}</p>", escape(&conditions_filter.to_string()))?;
+    if laps.is_empty() {
+        body.push_str("<p>No laps found.</p>");
+    } else {
+        let leader = laps[0].lap_time_ms;
+        let length_km = filter.track.length_km;
+        body.push_str(
+            "<table><tr><th>Pos</th><th>Driver</th><th>Car</th><th>Time</th><th>Gap</th>",
+        );
+        if length_km.is_some() {
+            body.push_str("<th>Avg speed</th>");
+        }
+        body.push_str("<th>Set</th></tr>");
+        for (index, lap) in laps.iter().enumerate() {
+            write!(
+                body,
+                "<tr><td>P{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>",
+                index + 1,
+                driver_link(&lap.driver_name),
+                escape(&catalogue.car_label(&lap.car_name, lap.car_display_name.as_deref())),
+                format_lap_time(Some(lap.lap_time_ms)),
+                if index > 0 {
+                    format!("+{}ms", lap.lap_time_ms - leader)
+                } else {
+                    "".to_string()
+                },
+            )?;
+            if let Some(length_km) = length_km {
+                write!(
+                    body,
+                    "<td>{:.1} km/h</td>",
+                    length_km * 3_600_000.0 / lap.lap_time_ms as f64
+                )?;
+            }
+            write!(body, "<td>{}</td></tr>", date(&lap.created_at))?;
+        }
+        body.push_str("</table>");
+    }
+    Ok(page(
+        &catalogue.track_label(
+            &filter.track.name,
+            filter.track.display_name.as_deref(),
+            &filter.track.layout,
+        ),
+        body,
+    ))
+}
+
+async fn driver(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+    Path(driver): Path<String>,
+    Query(conditions): Query<ConditionsQuery>,
+) -> PageResult {
+    if storage.lap_count(&driver).await?.is_none() {
+        return Err(ServerError::NotFound(format!("Unknown driver {driver}")).into());
+    }
+    let conditions = conditions.filter(&catalogue)?;
+    let pbs = storage.personal_bests(&driver, &conditions).await?;
+    let records: Vec<_> = storage
+        .records(&conditions)
+        .await?
+        .into_iter()
+        .filter(|record| record.driver_name == driver)
+        .collect();
+
+    let mut body = conditions_links(&format!("/drivers/{}", encode(&driver)), &conditions);
+    body.push_str("<h2>Personal bests</h2>");
+    if pbs.is_empty() {
+        body.push_str("<p>No laps found.</p>");
+    } else {
+        body.push_str(
+            "<table><tr><th>Track</th><th>Car</th><th>Category</th><th>Time</th><th>Set</th></tr>",
+        );
+        for pb in &pbs {
+            write!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                track_link(
+                    &catalogue,
+                    &pb.track_name,
+                    pb.track_display_name.as_deref(),
+                    &pb.track_layout
+                ),
+                escape(&catalogue.car_label(&pb.car_name, pb.car_display_name.as_deref())),
+                escape(&pb.category),
+                format_lap_time(Some(pb.lap_time_ms)),
+                date(&pb.created_at),
+            )?;
+        }
+        body.push_str("</table>");
+    }
+    body.push_str("<h2>Records held</h2>");
+    if records.is_empty() {
+        body.push_str("<p>None yet.</p>");
+    } else {
+        body.push_str(
+            "<table><tr><th>Track</th><th>Category</th><th>Car</th><th>Time</th><th>Set</th></tr>",
+        );
+        for record in &records {
+            write!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                track_link(
+                    &catalogue,
+                    &record.track_name,
+                    record.track_display_name.as_deref(),
+                    &record.track_layout
+                ),
+                escape(&record.category),
+                escape(&catalogue.car_label(&record.car_name, record.car_display_name.as_deref())),
+                format_lap_time(Some(record.lap_time_ms)),
+                date(&record.created_at),
+            )?;
+        }
+        body.push_str("</table>");
+    }
+    Ok(page(&driver, body))
+}
+
+async fn records(
+    State(storage): State<Arc<dyn Storage>>,
+    State(catalogue): State<Arc<Catalogue>>,
+) -> PageResult {
+    Ok(page(
+        "Recent records",
+        records_table(&catalogue, &storage.recent_records(50).await?),
+    ))
+}
+
+/// A transparent page for streaming software to show over the game, updated from `standings` events.
+async fn overlay() -> Html<&'static str> {
+    Html(include_str!("overlay.html"))
+}
+
+fn records_table(catalogue: &Catalogue, records: &[RecordSetRow]) -> String {
+    if records.is_empty() {
+        return "<p>No records have been set yet.</p>".to_string();
+    }
+    let mut table = String::from(
+        "<table><tr><th>Set</th><th>Track</th><th>Category</th><th>Conditions</th><th>Driver</th><th>Car</th><th>Time</th></tr>",
+    );
+    for record in records {
+        table.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            date(&record.created_at),
+            track_link(
+                catalogue,
+                &record.track_name,
+                record.track_display_name.as_deref(),
+                &record.track_layout
+            ),
+            escape(&record.category),
+            record.surface,
+            driver_link(&record.driver_name),
+            escape(&catalogue.car_label(&record.car_name, record.car_display_name.as_deref())),
+            format_lap_time(Some(record.lap_time_ms)),
+        ));
+    }
+    table.push_str("</table>");
+    table
+}
+
+/// Links for switching a page between dry and wet laps, and to laps from every BoP era.
+fn conditions_links(path: &str, conditions: &ConditionsFilter) -> String {
+    format!(
+        r#"<p>{} · <a href="{path}">Dry</a> · <a href="{path}?wet=true">Wet</a> · <a href="{path}?bop_era=all">All time</a></p>"#,
+        escape(&conditions.to_string())
+    )
+}
+
+fn track_link(
+    catalogue: &Catalogue,
+    track: &str,
+    display_name: Option<&str>,
+    layout: &str,
+) -> String {
+    let query = if layout.is_empty() {
+        String::new()
+    } else {
+        format!("?layout={}", encode(layout))
+    };
+    format!(
+        r#"<a href="/tracks/{}{query}">{}</a>"#,
+        encode(track),
+        escape(&catalogue.track_label(track, display_name, layout))
+    )
+}
+
+fn driver_link(driver: &str) -> String {
+    format!(
+        r#"<a href="/drivers/{}">{}</a>"#,
+        encode(driver),
+        escape(driver)
+    )
+}
+
+fn date(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d").to_string()
+}
+
+fn selected(is_selected: bool) -> &'static str {
+    if is_selected {
+        " selected"
+    } else {
+        ""
+    }
+}
+
+fn page(title: &str, body: String) -> Html<String> {
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em auto; max-width: 60em; padding: 0 1em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border-bottom: 1px solid #ddd; padding: 0.3em 0.6em; text-align: left; }}
+.more {{ font-size: 0.6em; }}
+</style>
+</head>
+<body>
+<nav><a href="/">Leaderboards</a> · <a href="/records">Recent records</a></nav>
+<h1>{title}</h1>
+{body}
+</body>
+</html>"#,
+        title = escape(title),
+    ))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes a path segment so names with spaces or slashes still link to the right page.
+fn encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
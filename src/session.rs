@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(sqlx::Type, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "session_type", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "snake_case")]
+pub enum SessionType {
+    Practice,
+    Qualifying,
+    Race,
+    Hotlap,
+    Unknown,
+}
+
+impl fmt::Display for SessionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            SessionType::Practice => "Practice",
+            SessionType::Qualifying => "Qualifying",
+            SessionType::Race => "Race",
+            SessionType::Hotlap => "Hotlap",
+            SessionType::Unknown => "Unknown session",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(sqlx::Type, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "rain_level", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "snake_case")]
+pub enum RainLevel {
+    NoRain,
+    Drizzle,
+    LightRain,
+    MediumRain,
+    HeavyRain,
+    Thunderstorm,
+}
+
+impl fmt::Display for RainLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            RainLevel::NoRain => "No rain",
+            RainLevel::Drizzle => "Drizzle",
+            RainLevel::LightRain => "Light rain",
+            RainLevel::MediumRain => "Medium rain",
+            RainLevel::HeavyRain => "Heavy rain",
+            RainLevel::Thunderstorm => "Thunderstorm",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(sqlx::Type, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "grip_status", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "snake_case")]
+pub enum GripStatus {
+    Green,
+    Fast,
+    Optimum,
+    Greasy,
+    Damp,
+    Wet,
+    Flooded,
+}
+
+impl fmt::Display for GripStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            GripStatus::Green => "Green",
+            GripStatus::Fast => "Fast",
+            GripStatus::Optimum => "Optimum",
+            GripStatus::Greasy => "Greasy",
+            GripStatus::Damp => "Damp",
+            GripStatus::Wet => "Wet",
+            GripStatus::Flooded => "Flooded",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Weather and track conditions at a point in a session.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Conditions {
+    pub(crate) air_temperature: f32,
+    pub(crate) track_temperature: f32,
+    pub(crate) rain_level: RainLevel,
+    pub(crate) grip_status: GripStatus,
+}
+
+impl fmt::Display for Conditions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Air {:.0}°C Track {:.0}°C {} {} grip",
+            self.air_temperature, self.track_temperature, self.rain_level, self.grip_status
+        )
+    }
+}
+
+impl Conditions {
+    pub(crate) fn surface(&self) -> Surface {
+        let wet_grip = matches!(
+            self.grip_status,
+            GripStatus::Damp | GripStatus::Wet | GripStatus::Flooded
+        );
+        if self.rain_level != RainLevel::NoRain || wet_grip {
+            Surface::Wet
+        } else {
+            Surface::Dry
+        }
+    }
+}
+
+#[derive(sqlx::Type, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[sqlx(type_name = "surface", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "snake_case")]
+pub enum Surface {
+    #[default]
+    Dry,
+    Wet,
+}
+
+impl fmt::Display for Surface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Surface::Dry => "Dry",
+            Surface::Wet => "Wet",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Which laps get ranked together. Laps are only compared against others on the same surface and in
+/// the same BoP era and, when temperature bands are enabled, with a track temperature in the same
+/// band.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct ConditionsFilter {
+    pub(crate) surface: Surface,
+    /// Lower (inclusive) and upper (exclusive) track temperature in °C.
+    pub(crate) track_temperature: Option<(f32, f32)>,
+    /// `None` ranks laps from every era together.
+    pub(crate) bop_era: Option<String>,
+}
+
+impl ConditionsFilter {
+    /// `band_width` splits track temperatures into bands of that many degrees, e.g. 10 gives 20-30°C.
+    pub(crate) fn new(
+        conditions: &Conditions,
+        band_width: Option<f32>,
+        bop_era: &str,
+    ) -> ConditionsFilter {
+        ConditionsFilter {
+            surface: conditions.surface(),
+            track_temperature: band_width.filter(|width| *width > 0.0).map(|width| {
+                let low = (conditions.track_temperature / width).floor() * width;
+                (low, low + width)
+            }),
+            bop_era: Some(bop_era.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ConditionsFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.track_temperature {
+            Some((low, high)) => write!(f, "{}, track {:.0}-{:.0}°C", self.surface, low, high)?,
+            None => write!(f, "{}", self.surface)?,
+        }
+        match &self.bop_era {
+            Some(bop_era) => write!(f, ", {bop_era} BoP"),
+            None => write!(f, ", all time"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditions(
+        track_temperature: f32,
+        rain_level: RainLevel,
+        grip_status: GripStatus,
+    ) -> Conditions {
+        Conditions {
+            air_temperature: 20.0,
+            track_temperature,
+            rain_level,
+            grip_status,
+        }
+    }
+
+    fn dry(track_temperature: f32) -> Conditions {
+        conditions(track_temperature, RainLevel::NoRain, GripStatus::Optimum)
+    }
+
+    #[test]
+    fn track_temperatures_on_a_band_edge_start_the_next_band() {
+        let band = |track_temperature| {
+            ConditionsFilter::new(&dry(track_temperature), Some(10.0), "2024").track_temperature
+        };
+        assert_eq!(band(20.0), Some((20.0, 30.0)));
+        assert_eq!(band(29.9), Some((20.0, 30.0)));
+        assert_eq!(band(30.0), Some((30.0, 40.0)));
+        assert_eq!(band(-0.5), Some((-10.0, 0.0)));
+
+        assert_eq!(
+            ConditionsFilter::new(&dry(29.9), None, "2024").track_temperature,
+            None
+        );
+        assert_eq!(
+            ConditionsFilter::new(&dry(29.9), Some(0.0), "2024").track_temperature,
+            None
+        );
+    }
+
+    #[test]
+    fn dry_and_wet_laps_are_ranked_apart() {
+        let dry = ConditionsFilter::new(&dry(25.0), Some(10.0), "2024");
+        let raining = ConditionsFilter::new(
+            &conditions(25.0, RainLevel::Drizzle, GripStatus::Optimum),
+            Some(10.0),
+            "2024",
+        );
+        let drying = ConditionsFilter::new(
+            &conditions(25.0, RainLevel::NoRain, GripStatus::Damp),
+            Some(10.0),
+            "2024",
+        );
+        let greasy = ConditionsFilter::new(
+            &conditions(25.0, RainLevel::NoRain, GripStatus::Greasy),
+            Some(10.0),
+            "2024",
+        );
+
+        assert_eq!(dry.surface, Surface::Dry);
+        assert_eq!(raining.surface, Surface::Wet);
+        assert_eq!(drying.surface, Surface::Wet);
+        assert_eq!(greasy, dry);
+        assert_ne!(raining, dry);
+        assert_eq!(dry.to_string(), "Dry, track 20-30°C, 2024 BoP");
+        assert_eq!(drying.to_string(), "Wet, track 20-30°C, 2024 BoP");
+    }
+}
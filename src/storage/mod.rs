@@ -0,0 +1,221 @@
+mod r#postgres;
+mod r#queue;
+mod r#sqlite;
+#[cfg(test)]
+pub(crate) mod r#testing;
+
+use crate::cars::CarCategory;
+use crate::catalogue::legacy_bop_era;
+use crate::session::{Conditions, ConditionsFilter, SessionType, Surface};
+use crate::{
+    BestLap, BestLapData, BestLapWithDriver, CarRow, CategoryRecordRow, Driver, PersonalBestRow,
+    RecentPersonalBestRow, RecordSetRow, TrackRow,
+};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+pub(crate) use r#postgres::PostgresStorage;
+pub(crate) use r#queue::QueuedStorage;
+pub(crate) use r#sqlite::{ExportedLap, ExportedSession, SqliteStorage};
+
+/// Where drivers, laps and sessions are kept.
+#[async_trait::async_trait]
+pub(crate) trait Storage: Send + Sync {
+    async fn upsert_driver(&self, name: &str) -> Result<Driver>;
+
+    /// Each layout of a track is a track of its own. Tracks recorded before layouts were known keep
+    /// an empty one, so their laps stay apart from every layout recorded since.
+    async fn upsert_track(&self, name: &str, layout: &TrackLayout) -> Result<TrackRow>;
+
+    async fn upsert_car(&self, name: &str, category: &CarCategory) -> Result<CarRow>;
+
+    /// Returns the new session's id.
+    async fn start_session(&self, session: &NewSession) -> Result<i64>;
+
+    async fn end_session(&self, session_id: i64, ended_at: DateTime<Utc>) -> Result<()>;
+
+    /// The id of the session a driver started at `started_at`, if it's been stored.
+    async fn find_session(&self, driver_id: i64, started_at: DateTime<Utc>) -> Result<Option<i64>>;
+
+    /// The id of the lap a driver completed at `created_at`, if it's been stored.
+    async fn find_lap(&self, driver_id: i64, created_at: DateTime<Utc>) -> Result<Option<i64>>;
+
+    /// Stores a lap along with its sector times, returning the lap's id.
+    async fn insert_lap(&self, lap: &NewLap) -> Result<i64>;
+
+    /// Replaces the driver's best lap in this car and surface unless it's already faster. Returns
+    /// the stored best lap when it was replaced.
+    async fn save_best_lap(&self, best_lap: &BestLapData) -> Result<Option<BestLap>>;
+
+    async fn discord_user_id(&self, driver_id: i64) -> Result<Option<String>>;
+
+    /// The sum of the driver's best sectors in a car, in the given conditions.
+    async fn theoretical_best(
+        &self,
+        driver_id: i64,
+        track_id: i64,
+        car_id: i64,
+        conditions: &ConditionsFilter,
+    ) -> Result<Option<i64>>;
+
+    async fn sectors(&self, lap_id: i64) -> Result<Vec<i64>>;
+
+    /// Each driver's fastest valid lap at a track in the given conditions, fastest first, in any of
+    /// `car_ids`. Passing neither cars nor a category ranks every car together.
+    async fn leaderboard(
+        &self,
+        track_id: i64,
+        car_ids: Option<&[i64]>,
+        category: Option<&str>,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<BestLapWithDriver>>;
+
+    async fn tracks(&self) -> Result<Vec<TrackRow>>;
+
+    /// Finds a layout of a track, or the latest one recorded when `layout` isn't given.
+    async fn find_track(&self, name: &str, layout: Option<&str>) -> Result<Option<TrackRow>>;
+
+    async fn cars(&self) -> Result<Vec<CarRow>>;
+
+    async fn find_car(&self, name: &str) -> Result<Option<CarRow>>;
+
+    /// Cars with laps at a track.
+    async fn cars_at_track(&self, track_id: i64) -> Result<Vec<CarRow>>;
+
+    async fn categories(&self) -> Result<Vec<String>>;
+
+    /// How many laps a driver has completed, or `None` for drivers that have never driven.
+    async fn lap_count(&self, driver_name: &str) -> Result<Option<i64>>;
+
+    /// A driver's fastest valid lap at every track and car they've driven, in the given conditions.
+    async fn personal_bests(
+        &self,
+        driver_name: &str,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<PersonalBestRow>>;
+
+    async fn recent_personal_bests(&self, limit: i64) -> Result<Vec<RecentPersonalBestRow>>;
+
+    /// The fastest valid lap in every category at every track, in the given conditions.
+    async fn records(&self, conditions: &ConditionsFilter) -> Result<Vec<CategoryRecordRow>>;
+
+    /// Laps that set a category record at the time they were driven, newest first, whether or not
+    /// they've been beaten since.
+    async fn recent_records(&self, limit: i64) -> Result<Vec<RecordSetRow>>;
+
+    /// Gives a track the tracker doesn't recognise a name to show. Returns `false` if the tracker
+    /// has never seen the track.
+    async fn name_track(&self, name: &str, display_name: &str) -> Result<bool>;
+
+    /// Gives a car the tracker doesn't recognise a name to show and a category, leaving whichever
+    /// isn't passed as it was. Returns `false` if the tracker has never seen the car.
+    async fn name_car(
+        &self,
+        name: &str,
+        display_name: Option<&str>,
+        category: Option<&CarCategory>,
+    ) -> Result<bool>;
+
+    /// Mentions a Discord user when the driver's records are beaten, replacing any user linked
+    /// before. Returns `false` if the tracker has never seen the driver.
+    async fn link_discord_user(&self, driver_name: &str, discord_user_id: &str) -> Result<bool>;
+
+    /// Passes an event on to everyone listening for them. Storage that can't reach other
+    /// processes drops it.
+    async fn publish_event(&self, payload: &str) -> Result<()>;
+
+    /// Starts listening for published events.
+    async fn listen_for_events(&self) -> Result<Box<dyn EventListener>>;
+}
+
+/// Receives the payloads of published events.
+#[async_trait::async_trait]
+pub(crate) trait EventListener: Send {
+    async fn recv(&mut self) -> Result<String>;
+}
+
+/// What's known about the layout of a track being driven.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct TrackLayout {
+    /// Empty when the catalogue doesn't have the track.
+    pub(crate) layout: String,
+    pub(crate) length_km: Option<f64>,
+    pub(crate) sector_count: Option<i32>,
+    pub(crate) country: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct NewSession {
+    pub(crate) driver_id: i64,
+    pub(crate) track_id: i64,
+    pub(crate) car_id: i64,
+    pub(crate) session_index: i32,
+    pub(crate) session_type: SessionType,
+    pub(crate) conditions: Conditions,
+    pub(crate) started_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct NewLap {
+    pub(crate) driver_id: i64,
+    pub(crate) track_id: i64,
+    pub(crate) car_id: i64,
+    pub(crate) session_id: i64,
+    pub(crate) lap_number: i32,
+    pub(crate) lap_time_ms: i64,
+    pub(crate) is_valid: bool,
+    pub(crate) invalid_reason: Option<String>,
+    pub(crate) surface: Surface,
+    pub(crate) track_temperature: f32,
+    /// Empty when the sectors couldn't be timed.
+    pub(crate) sectors_ms: Vec<i64>,
+    pub(crate) created_at: DateTime<Utc>,
+    /// Missing, like the era, from laps queued before they were recorded.
+    #[serde(default)]
+    pub(crate) game_version: Option<String>,
+    #[serde(default = "legacy_bop_era")]
+    pub(crate) bop_era: String,
+}
+
+/// Connects to the database at `url` and brings its schema up to date. `postgres://` URLs use
+/// Postgres and `sqlite:` URLs a local SQLite file, created if it doesn't exist.
+pub(crate) async fn connect(url: &str) -> Result<Arc<dyn Storage>> {
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        Ok(Arc::new(PostgresStorage::connect(url).await?))
+    } else if url.starts_with("sqlite:") {
+        Ok(Arc::new(SqliteStorage::connect(url).await?))
+    } else {
+        bail!("Unsupported DATABASE_URL {url}, expected a postgres:// or sqlite: URL")
+    }
+}
+
+/// Like [`connect`], but writes that can't reach a Postgres database are queued in a local SQLite
+/// file at `queue_path` and replayed once it's back.
+pub(crate) async fn connect_queued(url: &str, queue_path: &Path) -> Result<Arc<dyn Storage>> {
+    let storage = connect(url).await?;
+    if url.starts_with("sqlite:") {
+        return Ok(storage);
+    }
+    Ok(Arc::new(QueuedStorage::open(storage, queue_path).await?))
+}
+
+/// Whether an error means the database couldn't be reached, rather than that it rejected a query.
+pub(crate) fn is_unreachable(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<sqlx::Error>() {
+        Some(
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed,
+        ) => true,
+        // Connection exceptions, and the server shutting down or starting up.
+        Some(sqlx::Error::Database(error)) => error
+            .code()
+            .is_some_and(|code| code.starts_with("08") || code.starts_with("57P")),
+        _ => false,
+    }
+}
@@ -0,0 +1,521 @@
+use super::{EventListener, NewLap, NewSession, Storage, TrackLayout};
+use crate::cars::CarCategory;
+use crate::session::{ConditionsFilter, Surface};
+use crate::{
+    BestLap, BestLapData, BestLapWithDriver, CarRow, CategoryRecordRow, Driver, PersonalBestRow,
+    RecentPersonalBestRow, RecordSetRow, TrackRow,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgListener, PgPoolOptions};
+use sqlx::{Pool, Postgres};
+use std::time::Duration;
+
+/// The Postgres channel events are published on.
+const EVENT_CHANNEL: &str = "sim_leaderboards_events";
+
+pub(crate) struct PostgresStorage {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresStorage {
+    pub(crate) async fn connect(url: &str) -> Result<PostgresStorage> {
+        // Give up on an unreachable database quickly, so the tracker can queue writes instead.
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .acquire_timeout(Duration::from_secs(5))
+            .connect(url)
+            .await?;
+        sqlx::migrate!("migrations/postgres").run(&pool).await?;
+        Ok(PostgresStorage { pool })
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for PostgresStorage {
+    async fn upsert_driver(&self, name: &str) -> Result<Driver> {
+        Ok(sqlx::query_as!(
+            Driver,
+            "INSERT INTO driver (name) VALUES ($1) ON CONFLICT (name) DO UPDATE set name=$1 RETURNING *",
+            name
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn upsert_track(&self, name: &str, layout: &TrackLayout) -> Result<TrackRow> {
+        Ok(sqlx::query_as!(
+            TrackRow,
+            "INSERT INTO track (name, layout, length_km, sector_count, country) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (name, layout) DO UPDATE set length_km = COALESCE($3, track.length_km), sector_count = COALESCE($4, track.sector_count), country = COALESCE($5, track.country) RETURNING id, name, display_name, layout, length_km, sector_count, country",
+            name,
+            layout.layout,
+            layout.length_km,
+            layout.sector_count,
+            layout.country
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn upsert_car(&self, name: &str, category: &CarCategory) -> Result<CarRow> {
+        Ok(sqlx::query_as!(
+            CarRow,
+            r#"INSERT INTO car (name, category) VALUES ($1, $2::text::category) ON CONFLICT (name) DO UPDATE set category = CASE WHEN car.category = 'UNKNOWN' THEN EXCLUDED.category ELSE car.category END RETURNING id, name, category::text as "category!", display_name"#,
+            name,
+            &category.to_string()
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn start_session(&self, session: &NewSession) -> Result<i64> {
+        Ok(sqlx::query_scalar!(
+            "INSERT INTO session (driver_id, track_id, car_id, session_index, session_type, air_temperature, track_temperature, rain_level, grip_status, started_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id",
+            session.driver_id,
+            session.track_id,
+            session.car_id,
+            session.session_index,
+            session.session_type as _,
+            session.conditions.air_temperature,
+            session.conditions.track_temperature,
+            session.conditions.rain_level as _,
+            session.conditions.grip_status as _,
+            session.started_at
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn end_session(&self, session_id: i64, ended_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE session SET ended_at = $2 WHERE id = $1",
+            session_id,
+            ended_at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_session(&self, driver_id: i64, started_at: DateTime<Utc>) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar!(
+            "SELECT id FROM session WHERE driver_id = $1 AND started_at = $2",
+            driver_id,
+            started_at
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn find_lap(&self, driver_id: i64, created_at: DateTime<Utc>) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar!(
+            "SELECT id FROM lap WHERE driver_id = $1 AND created_at = $2",
+            driver_id,
+            created_at
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn insert_lap(&self, lap: &NewLap) -> Result<i64> {
+        // The lap and its sectors are stored together, so a lap that's queued because the
+        // connection dropped halfway through isn't stored twice.
+        let mut transaction = self.pool.begin().await?;
+        let lap_id = sqlx::query_scalar!(
+            "INSERT INTO lap (driver_id, track_id, car_id, session_id, lap_number, lap_time_ms, is_valid, invalid_reason, surface, track_temperature, created_at, game_version, bop_era) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING id",
+            lap.driver_id,
+            lap.track_id,
+            lap.car_id,
+            lap.session_id,
+            lap.lap_number,
+            lap.lap_time_ms,
+            lap.is_valid,
+            lap.invalid_reason,
+            lap.surface as _,
+            lap.track_temperature,
+            lap.created_at,
+            lap.game_version,
+            lap.bop_era
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        for (sector_index, sector_time_ms) in lap.sectors_ms.iter().enumerate() {
+            sqlx::query!(
+                "INSERT INTO lap_sector (lap_id, sector_index, sector_time_ms) VALUES ($1, $2, $3)",
+                lap_id,
+                sector_index as i32,
+                sector_time_ms
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(lap_id)
+    }
+
+    async fn save_best_lap(&self, best_lap: &BestLapData) -> Result<Option<BestLap>> {
+        Ok(sqlx::query_as!(
+            BestLap,
+            r#"INSERT INTO best_lap (driver_id, track_id, car_id, created_at, lap_time_ms, lap_id, surface, bop_era) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (driver_id, track_id, car_id, surface, bop_era) DO UPDATE set lap_time_ms=$5, lap_id=$6, created_at=$4 WHERE best_lap.lap_time_ms > $5 RETURNING id, driver_id, track_id, created_at, lap_time_ms, car_id, lap_id, surface as "surface: Surface", bop_era"#,
+            best_lap.driver_id,
+            best_lap.track_id,
+            best_lap.car_id,
+            best_lap.created_at,
+            best_lap.lap_time_ms,
+            best_lap.lap_id,
+            best_lap.surface as _,
+            best_lap.bop_era
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn discord_user_id(&self, driver_id: i64) -> Result<Option<String>> {
+        Ok(sqlx::query_scalar!(
+            "SELECT discord_user_id FROM driver_discord_user WHERE driver_id = $1",
+            driver_id
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn theoretical_best(
+        &self,
+        driver_id: i64,
+        track_id: i64,
+        car_id: i64,
+        conditions: &ConditionsFilter,
+    ) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT SUM(best_sector)::BIGINT as "theoretical_best"
+       FROM (SELECT MIN(lap_sector.sector_time_ms) as best_sector
+             from lap_sector
+                      INNER JOIN lap l on l.id = lap_sector.lap_id
+             WHERE l.driver_id = $1 AND l.track_id = $2 AND l.car_id = $3 AND l.is_valid
+               AND l.surface = $4
+               AND ($5::real IS NULL OR l.track_temperature >= $5)
+               AND ($6::real IS NULL OR l.track_temperature < $6)
+               AND ($7::text IS NULL OR l.bop_era = $7)
+             GROUP BY lap_sector.sector_index) best_sectors"#,
+            driver_id,
+            track_id,
+            car_id,
+            conditions.surface as _,
+            conditions.track_temperature.map(|(low, _)| low),
+            conditions.track_temperature.map(|(_, high)| high),
+            conditions.bop_era
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn sectors(&self, lap_id: i64) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar!(
+            "SELECT sector_time_ms FROM lap_sector WHERE lap_id = $1 ORDER BY sector_index ASC",
+            lap_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn leaderboard(
+        &self,
+        track_id: i64,
+        car_ids: Option<&[i64]>,
+        category: Option<&str>,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<BestLapWithDriver>> {
+        Ok(sqlx::query_as::<_, BestLapWithDriver>(
+            r#"SELECT * FROM (SELECT DISTINCT ON (lap.driver_id) lap.id,
+       lap.track_id,
+       lap.driver_id,
+       lap.lap_time_ms,
+       lap.created_at,
+       lap.car_id,
+       d."name" as driver_name,
+       c.name     as car_name,
+       c.display_name as car_display_name
+       from lap
+         INNER JOIN public.driver d on d.id = lap.driver_id
+         INNER JOIN public.car c on c.id = lap.car_id
+       WHERE lap.is_valid AND lap.track_id = $1
+         AND ($2::bigint[] IS NULL OR c.id = ANY($2))
+         AND ($3::text IS NULL OR c.category = $3::category)
+         AND lap.surface = $4
+         AND ($5::real IS NULL OR lap.track_temperature >= $5)
+         AND ($6::real IS NULL OR lap.track_temperature < $6)
+         AND ($7::text IS NULL OR lap.bop_era = $7)
+       ORDER BY lap.driver_id, lap.lap_time_ms ASC, lap.created_at ASC) best
+       ORDER BY lap_time_ms ASC, created_at ASC"#,
+        )
+        .bind(track_id)
+        .bind(car_ids)
+        .bind(category)
+        .bind(conditions.surface)
+        .bind(conditions.track_temperature.map(|(low, _)| low))
+        .bind(conditions.track_temperature.map(|(_, high)| high))
+        .bind(&conditions.bop_era)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn tracks(&self) -> Result<Vec<TrackRow>> {
+        Ok(sqlx::query_as!(
+            TrackRow,
+            "SELECT id, name, display_name, layout, length_km, sector_count, country FROM track ORDER BY name, id"
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn find_track(&self, name: &str, layout: Option<&str>) -> Result<Option<TrackRow>> {
+        Ok(sqlx::query_as!(
+            TrackRow,
+            "SELECT id, name, display_name, layout, length_km, sector_count, country FROM track WHERE name = $1 AND ($2::text IS NULL OR layout = $2) ORDER BY id DESC LIMIT 1",
+            name,
+            layout
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn cars(&self) -> Result<Vec<CarRow>> {
+        Ok(sqlx::query_as!(
+            CarRow,
+            r#"SELECT id, name, category::text as "category!", display_name FROM car ORDER BY category, name"#
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn find_car(&self, name: &str) -> Result<Option<CarRow>> {
+        Ok(sqlx::query_as!(
+            CarRow,
+            r#"SELECT id, name, category::text as "category!", display_name FROM car WHERE name = $1"#,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn cars_at_track(&self, track_id: i64) -> Result<Vec<CarRow>> {
+        Ok(sqlx::query_as!(
+            CarRow,
+            r#"SELECT DISTINCT c.id, c.name, c.category::text as "category!", c.display_name
+       FROM lap l
+                INNER JOIN car c on c.id = l.car_id
+       WHERE l.track_id = $1
+       ORDER BY 3, 2"#,
+            track_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn categories(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT DISTINCT category::text as "category!" FROM car ORDER BY 1"#
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn lap_count(&self, driver_name: &str) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT COUNT(l.id) as "count!" FROM driver d LEFT JOIN lap l on l.driver_id = d.id WHERE d.name = $1 GROUP BY d.id"#,
+            driver_name
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn personal_bests(
+        &self,
+        driver_name: &str,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<PersonalBestRow>> {
+        Ok(sqlx::query_as!(
+            PersonalBestRow,
+            r#"SELECT DISTINCT ON (t.name, t.layout, c.name) t.name as track_name,
+       t.display_name as track_display_name,
+       t.layout as track_layout,
+       c.name     as car_name,
+       c.display_name as car_display_name,
+       c.category::text as "category!",
+       l.lap_time_ms,
+       l.created_at
+       FROM lap l
+                INNER JOIN driver d on d.id = l.driver_id
+                INNER JOIN track t on t.id = l.track_id
+                INNER JOIN car c on c.id = l.car_id
+       WHERE d.name = $1 AND l.is_valid AND l.surface = $2
+         AND ($3::real IS NULL OR l.track_temperature >= $3)
+         AND ($4::real IS NULL OR l.track_temperature < $4)
+         AND ($5::text IS NULL OR l.bop_era = $5)
+       ORDER BY t.name, t.layout, c.name, l.lap_time_ms ASC, l.created_at ASC"#,
+            driver_name,
+            conditions.surface as _,
+            conditions.track_temperature.map(|(low, _)| low),
+            conditions.track_temperature.map(|(_, high)| high),
+            conditions.bop_era
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn recent_personal_bests(&self, limit: i64) -> Result<Vec<RecentPersonalBestRow>> {
+        Ok(sqlx::query_as!(
+            RecentPersonalBestRow,
+            r#"SELECT d.name as driver_name,
+       t.name as track_name,
+       t.display_name as track_display_name,
+       t.layout as track_layout,
+       c.name as car_name,
+       c.display_name as car_display_name,
+       c.category::text as "category!",
+       best_lap.surface as "surface: Surface",
+       best_lap.lap_time_ms,
+       best_lap.created_at
+       FROM best_lap
+                INNER JOIN driver d on d.id = best_lap.driver_id
+                INNER JOIN track t on t.id = best_lap.track_id
+                INNER JOIN car c on c.id = best_lap.car_id
+       ORDER BY best_lap.created_at DESC
+       LIMIT $1"#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn records(&self, conditions: &ConditionsFilter) -> Result<Vec<CategoryRecordRow>> {
+        Ok(sqlx::query_as!(
+            CategoryRecordRow,
+            r#"SELECT DISTINCT ON (t.name, t.layout, c.category) t.name as track_name,
+       t.display_name as track_display_name,
+       t.layout as track_layout,
+       c.category::text as "category!",
+       d.name     as driver_name,
+       c.name     as car_name,
+       c.display_name as car_display_name,
+       l.lap_time_ms,
+       l.created_at
+       FROM lap l
+                INNER JOIN driver d on d.id = l.driver_id
+                INNER JOIN track t on t.id = l.track_id
+                INNER JOIN car c on c.id = l.car_id
+       WHERE l.is_valid AND l.surface = $1
+         AND ($2::real IS NULL OR l.track_temperature >= $2)
+         AND ($3::real IS NULL OR l.track_temperature < $3)
+         AND ($4::text IS NULL OR l.bop_era = $4)
+       ORDER BY t.name, t.layout, c.category, l.lap_time_ms ASC, l.created_at ASC"#,
+            conditions.surface as _,
+            conditions.track_temperature.map(|(low, _)| low),
+            conditions.track_temperature.map(|(_, high)| high),
+            conditions.bop_era
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn recent_records(&self, limit: i64) -> Result<Vec<RecordSetRow>> {
+        Ok(sqlx::query_as!(
+            RecordSetRow,
+            r#"SELECT t.name as track_name,
+       t.display_name as track_display_name,
+       t.layout as track_layout,
+       c.category::text as "category!",
+       l.surface as "surface: Surface",
+       d.name     as driver_name,
+       c.name     as car_name,
+       c.display_name as car_display_name,
+       l.lap_time_ms,
+       l.created_at
+       FROM lap l
+                INNER JOIN driver d on d.id = l.driver_id
+                INNER JOIN track t on t.id = l.track_id
+                INNER JOIN car c on c.id = l.car_id
+       WHERE l.is_valid
+         AND NOT EXISTS (SELECT 1
+                         FROM lap earlier
+                                  INNER JOIN car ec on ec.id = earlier.car_id
+                         WHERE earlier.is_valid
+                           AND earlier.track_id = l.track_id
+                           AND earlier.surface = l.surface
+                           AND earlier.bop_era = l.bop_era
+                           AND ec.category = c.category
+                           AND earlier.lap_time_ms <= l.lap_time_ms
+                           AND earlier.created_at < l.created_at)
+       ORDER BY l.created_at DESC
+       LIMIT $1"#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn name_track(&self, name: &str, display_name: &str) -> Result<bool> {
+        Ok(sqlx::query!(
+            "UPDATE track SET display_name = $2 WHERE name = $1",
+            name,
+            display_name
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+
+    async fn name_car(
+        &self,
+        name: &str,
+        display_name: Option<&str>,
+        category: Option<&CarCategory>,
+    ) -> Result<bool> {
+        Ok(sqlx::query!(
+            "UPDATE car SET display_name = COALESCE($2, display_name), category = COALESCE($3::text::category, category) WHERE name = $1",
+            name,
+            display_name,
+            category.map(|category| category.to_string())
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+
+    async fn link_discord_user(&self, driver_name: &str, discord_user_id: &str) -> Result<bool> {
+        Ok(sqlx::query!(
+            "INSERT INTO driver_discord_user (driver_id, discord_user_id) SELECT id, $2 FROM driver WHERE name = $1 ON CONFLICT (driver_id) DO UPDATE SET discord_user_id = EXCLUDED.discord_user_id",
+            driver_name,
+            discord_user_id
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+
+    async fn publish_event(&self, payload: &str) -> Result<()> {
+        sqlx::query!("SELECT pg_notify($1, $2)", EVENT_CHANNEL, payload)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn listen_for_events(&self) -> Result<Box<dyn EventListener>> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(EVENT_CHANNEL).await?;
+        Ok(Box::new(PostgresEventListener(listener)))
+    }
+}
+
+struct PostgresEventListener(PgListener);
+
+#[async_trait::async_trait]
+impl EventListener for PostgresEventListener {
+    async fn recv(&mut self) -> Result<String> {
+        Ok(self.0.recv().await?.payload().to_string())
+    }
+}
@@ -0,0 +1,879 @@
+use super::{is_unreachable, EventListener, NewLap, NewSession, Storage, TrackLayout};
+use crate::cars::CarCategory;
+use crate::session::ConditionsFilter;
+use crate::{
+    BestLap, BestLapData, BestLapWithDriver, CarRow, CategoryRecordRow, Driver, PersonalBestRow,
+    RecentPersonalBestRow, RecordSetRow, TrackRow,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// How long to queue writes without trying the database once it's found unreachable, so the tracker
+/// doesn't wait on it for every write.
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Queues writes in a local SQLite file while the database can't be reached, and replays them in
+/// order once it's back. Writes that create a row get a negative placeholder id while queued,
+/// which is swapped for the real id when they're replayed.
+///
+/// Best laps go through [`Storage::save_best_lap`] when replayed, which never replaces a faster
+/// lap, so a queued best lap can't overwrite one set elsewhere in the meantime.
+pub(crate) struct QueuedStorage {
+    storage: Arc<dyn Storage>,
+    queue: Pool<Sqlite>,
+    /// The real id behind every replayed placeholder. Writes hold the lock so they stay in order.
+    ids: Mutex<HashMap<i64, i64>>,
+    unreachable_at: std::sync::Mutex<Option<Instant>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "write", rename_all = "snake_case")]
+enum QueuedWrite {
+    UpsertDriver {
+        name: String,
+    },
+    UpsertTrack {
+        name: String,
+        /// Missing from tracks queued before layouts were recorded.
+        #[serde(default)]
+        layout: TrackLayout,
+    },
+    UpsertCar {
+        name: String,
+        category: String,
+    },
+    StartSession(NewSession),
+    EndSession {
+        session_id: i64,
+        ended_at: DateTime<Utc>,
+    },
+    InsertLap(NewLap),
+    SaveBestLap(BestLapData),
+}
+
+impl QueuedWrite {
+    /// Whether other writes can refer to the row this creates by its id.
+    fn creates_row(&self) -> bool {
+        !matches!(
+            self,
+            QueuedWrite::EndSession { .. } | QueuedWrite::SaveBestLap(_)
+        )
+    }
+
+    /// Swaps the placeholders of replayed writes for their real ids.
+    fn resolve_ids(&mut self, ids: &HashMap<i64, i64>) {
+        let resolve = |id: &mut i64| *id = resolve(ids, *id);
+        match self {
+            QueuedWrite::UpsertDriver { .. }
+            | QueuedWrite::UpsertTrack { .. }
+            | QueuedWrite::UpsertCar { .. } => {}
+            QueuedWrite::StartSession(session) => {
+                resolve(&mut session.driver_id);
+                resolve(&mut session.track_id);
+                resolve(&mut session.car_id);
+            }
+            QueuedWrite::EndSession { session_id, .. } => resolve(session_id),
+            QueuedWrite::InsertLap(lap) => {
+                resolve(&mut lap.driver_id);
+                resolve(&mut lap.track_id);
+                resolve(&mut lap.car_id);
+                resolve(&mut lap.session_id);
+            }
+            QueuedWrite::SaveBestLap(best_lap) => {
+                resolve(&mut best_lap.driver_id);
+                resolve(&mut best_lap.track_id);
+                resolve(&mut best_lap.car_id);
+                resolve(&mut best_lap.lap_id);
+            }
+        }
+    }
+}
+
+fn resolve(ids: &HashMap<i64, i64>, id: i64) -> i64 {
+    ids.get(&id).copied().unwrap_or(id)
+}
+
+impl QueuedStorage {
+    /// Opens the queue at `path`, creating it if it doesn't exist, and replays anything left in it
+    /// from the last time the tracker ran.
+    pub(crate) async fn open(storage: Arc<dyn Storage>, path: &Path) -> Result<QueuedStorage> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let queue = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!("migrations/queue").run(&queue).await?;
+        let ids = sqlx::query_as::<_, (i64, i64)>("SELECT placeholder, id FROM replayed_id")
+            .fetch_all(&queue)
+            .await?
+            .into_iter()
+            .collect();
+        let storage = QueuedStorage {
+            storage,
+            queue,
+            ids: Mutex::new(ids),
+            unreachable_at: std::sync::Mutex::new(None),
+        };
+
+        let mut ids = storage.ids.lock().await;
+        let replayed = storage
+            .replay(&mut ids)
+            .await
+            .with_context(|| format!("Couldn't replay the offline queue in {}", path.display()))?;
+        // Nothing refers to placeholders handed out before this run once the queue is empty.
+        if replayed {
+            sqlx::query("DELETE FROM replayed_id")
+                .execute(&storage.queue)
+                .await?;
+            ids.clear();
+        }
+        drop(ids);
+        Ok(storage)
+    }
+
+    /// Writes straight to the database when nothing is queued and it can be reached, and queues the
+    /// write otherwise. Returns the id of the row the write created, which is a placeholder if it
+    /// was queued.
+    async fn write(&self, mut write: QueuedWrite) -> Result<Option<i64>> {
+        let mut ids = self.ids.lock().await;
+        if !self.recently_unreachable() && self.replay(&mut ids).await? {
+            write.resolve_ids(&ids);
+            match self.apply(&write).await {
+                Ok(id) => return Ok(id),
+                Err(error) if is_unreachable(&error) => self.mark_unreachable(),
+                Err(error) => return Err(error),
+            }
+        }
+
+        // Fetching a RETURNING row doesn't wait for the insert to be committed, which could be lost
+        // if the tracker quits straight after.
+        let queue_id = sqlx::query("INSERT INTO queued_write (payload) VALUES (?1)")
+            .bind(serde_json::to_string(&write)?)
+            .execute(&self.queue)
+            .await?
+            .last_insert_rowid();
+        Ok(write.creates_row().then_some(-queue_id))
+    }
+
+    /// Like [`QueuedStorage::write`], for writes that create a row.
+    async fn write_row(&self, write: QueuedWrite) -> Result<i64> {
+        self.write(write)
+            .await?
+            .context("The write didn't create a row")
+    }
+
+    /// Replays queued writes, oldest first. Returns `false` if the database still can't be
+    /// reached, leaving the rest queued.
+    async fn replay(&self, ids: &mut HashMap<i64, i64>) -> Result<bool> {
+        while let Some((queue_id, payload)) = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, payload FROM queued_write ORDER BY id LIMIT 1",
+        )
+        .fetch_optional(&self.queue)
+        .await?
+        {
+            let mut write: QueuedWrite = serde_json::from_str(&payload)?;
+            write.resolve_ids(ids);
+            let id = match self.apply(&write).await {
+                Ok(id) => id,
+                Err(error) if is_unreachable(&error) => {
+                    self.mark_unreachable();
+                    return Ok(false);
+                }
+                Err(error) => {
+                    return Err(error.context(format!("Couldn't replay queued write {queue_id}")))
+                }
+            };
+
+            let mut transaction = self.queue.begin().await?;
+            let id = id.filter(|_| write.creates_row());
+            if let Some(id) = id {
+                sqlx::query("INSERT INTO replayed_id (placeholder, id) VALUES (?1, ?2)")
+                    .bind(-queue_id)
+                    .bind(id)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+            sqlx::query("DELETE FROM queued_write WHERE id = ?1")
+                .bind(queue_id)
+                .execute(&mut *transaction)
+                .await?;
+            transaction.commit().await?;
+            if let Some(id) = id {
+                ids.insert(-queue_id, id);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Makes a write against the database, returning the id of the row it created or replaced.
+    async fn apply(&self, write: &QueuedWrite) -> Result<Option<i64>> {
+        Ok(match write {
+            QueuedWrite::UpsertDriver { name } => Some(self.storage.upsert_driver(name).await?.id),
+            QueuedWrite::UpsertTrack { name, layout } => {
+                Some(self.storage.upsert_track(name, layout).await?.id)
+            }
+            QueuedWrite::UpsertCar { name, category } => {
+                let category: CarCategory = category
+                    .parse()
+                    .map_err(|_| anyhow!("Unknown category {category}"))?;
+                Some(self.storage.upsert_car(name, &category).await?.id)
+            }
+            QueuedWrite::StartSession(session) => Some(self.storage.start_session(session).await?),
+            QueuedWrite::EndSession {
+                session_id,
+                ended_at,
+            } => {
+                self.storage.end_session(*session_id, *ended_at).await?;
+                None
+            }
+            QueuedWrite::InsertLap(lap) => Some(self.storage.insert_lap(lap).await?),
+            QueuedWrite::SaveBestLap(best_lap) => self
+                .storage
+                .save_best_lap(best_lap)
+                .await?
+                .map(|best_lap| best_lap.id),
+        })
+    }
+
+    /// Reads back a row once the write that created it has reached the database, for the names and
+    /// categories admins have given it. Returns `None` while the write is queued.
+    async fn read_back<T>(
+        &self,
+        id: i64,
+        read: impl Future<Output = Result<Option<T>>>,
+    ) -> Result<Option<T>> {
+        if id < 0 {
+            return Ok(None);
+        }
+        match read.await {
+            Err(error) if is_unreachable(&error) => {
+                self.mark_unreachable();
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+
+    fn recently_unreachable(&self) -> bool {
+        self.unreachable_at
+            .lock()
+            .unwrap()
+            .is_some_and(|at| at.elapsed() < RETRY_AFTER)
+    }
+
+    fn mark_unreachable(&self) {
+        *self.unreachable_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    async fn resolve(&self, id: i64) -> i64 {
+        resolve(&*self.ids.lock().await, id)
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for QueuedStorage {
+    async fn upsert_driver(&self, name: &str) -> Result<Driver> {
+        let name = name.to_string();
+        Ok(Driver {
+            id: self
+                .write_row(QueuedWrite::UpsertDriver { name: name.clone() })
+                .await?,
+            name,
+        })
+    }
+
+    async fn upsert_track(&self, name: &str, layout: &TrackLayout) -> Result<TrackRow> {
+        let id = self
+            .write_row(QueuedWrite::UpsertTrack {
+                name: name.to_string(),
+                layout: layout.clone(),
+            })
+            .await?;
+        Ok(self
+            .read_back(id, self.storage.find_track(name, Some(&layout.layout)))
+            .await?
+            .unwrap_or_else(|| TrackRow {
+                id,
+                name: name.to_string(),
+                display_name: None,
+                layout: layout.layout.clone(),
+                length_km: layout.length_km,
+                sector_count: layout.sector_count,
+                country: layout.country.clone(),
+            }))
+    }
+
+    async fn upsert_car(&self, name: &str, category: &CarCategory) -> Result<CarRow> {
+        let id = self
+            .write_row(QueuedWrite::UpsertCar {
+                name: name.to_string(),
+                category: category.to_string(),
+            })
+            .await?;
+        Ok(self
+            .read_back(id, self.storage.find_car(name))
+            .await?
+            .unwrap_or_else(|| CarRow {
+                id,
+                name: name.to_string(),
+                category: category.to_string(),
+                display_name: None,
+            }))
+    }
+
+    async fn start_session(&self, session: &NewSession) -> Result<i64> {
+        self.write_row(QueuedWrite::StartSession(session.clone()))
+            .await
+    }
+
+    async fn end_session(&self, session_id: i64, ended_at: DateTime<Utc>) -> Result<()> {
+        self.write(QueuedWrite::EndSession {
+            session_id,
+            ended_at,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn find_session(&self, driver_id: i64, started_at: DateTime<Utc>) -> Result<Option<i64>> {
+        self.storage
+            .find_session(self.resolve(driver_id).await, started_at)
+            .await
+    }
+
+    async fn find_lap(&self, driver_id: i64, created_at: DateTime<Utc>) -> Result<Option<i64>> {
+        self.storage
+            .find_lap(self.resolve(driver_id).await, created_at)
+            .await
+    }
+
+    async fn insert_lap(&self, lap: &NewLap) -> Result<i64> {
+        self.write_row(QueuedWrite::InsertLap(lap.clone())).await
+    }
+
+    /// Returns `None` when the best lap was queued, as whether it'll replace the stored one isn't
+    /// known until it's replayed.
+    async fn save_best_lap(&self, best_lap: &BestLapData) -> Result<Option<BestLap>> {
+        let Some(id) = self
+            .write(QueuedWrite::SaveBestLap(best_lap.clone()))
+            .await?
+        else {
+            return Ok(None);
+        };
+        let ids = self.ids.lock().await;
+        Ok(Some(BestLap {
+            id,
+            driver_id: resolve(&ids, best_lap.driver_id),
+            track_id: resolve(&ids, best_lap.track_id),
+            created_at: best_lap.created_at,
+            lap_time_ms: best_lap.lap_time_ms,
+            car_id: resolve(&ids, best_lap.car_id),
+            lap_id: resolve(&ids, best_lap.lap_id),
+            surface: best_lap.surface,
+            bop_era: best_lap.bop_era.clone(),
+        }))
+    }
+
+    async fn discord_user_id(&self, driver_id: i64) -> Result<Option<String>> {
+        self.storage
+            .discord_user_id(self.resolve(driver_id).await)
+            .await
+    }
+
+    async fn theoretical_best(
+        &self,
+        driver_id: i64,
+        track_id: i64,
+        car_id: i64,
+        conditions: &ConditionsFilter,
+    ) -> Result<Option<i64>> {
+        self.storage
+            .theoretical_best(
+                self.resolve(driver_id).await,
+                self.resolve(track_id).await,
+                self.resolve(car_id).await,
+                conditions,
+            )
+            .await
+    }
+
+    async fn sectors(&self, lap_id: i64) -> Result<Vec<i64>> {
+        self.storage.sectors(self.resolve(lap_id).await).await
+    }
+
+    async fn leaderboard(
+        &self,
+        track_id: i64,
+        car_ids: Option<&[i64]>,
+        category: Option<&str>,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<BestLapWithDriver>> {
+        let car_ids = match car_ids {
+            Some(car_ids) => {
+                let mut resolved = Vec::with_capacity(car_ids.len());
+                for &car_id in car_ids {
+                    resolved.push(self.resolve(car_id).await);
+                }
+                Some(resolved)
+            }
+            None => None,
+        };
+        self.storage
+            .leaderboard(
+                self.resolve(track_id).await,
+                car_ids.as_deref(),
+                category,
+                conditions,
+            )
+            .await
+    }
+
+    async fn tracks(&self) -> Result<Vec<TrackRow>> {
+        self.storage.tracks().await
+    }
+
+    async fn find_track(&self, name: &str, layout: Option<&str>) -> Result<Option<TrackRow>> {
+        self.storage.find_track(name, layout).await
+    }
+
+    async fn cars(&self) -> Result<Vec<CarRow>> {
+        self.storage.cars().await
+    }
+
+    async fn find_car(&self, name: &str) -> Result<Option<CarRow>> {
+        self.storage.find_car(name).await
+    }
+
+    async fn cars_at_track(&self, track_id: i64) -> Result<Vec<CarRow>> {
+        self.storage
+            .cars_at_track(self.resolve(track_id).await)
+            .await
+    }
+
+    async fn categories(&self) -> Result<Vec<String>> {
+        self.storage.categories().await
+    }
+
+    async fn lap_count(&self, driver_name: &str) -> Result<Option<i64>> {
+        self.storage.lap_count(driver_name).await
+    }
+
+    async fn personal_bests(
+        &self,
+        driver_name: &str,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<PersonalBestRow>> {
+        self.storage.personal_bests(driver_name, conditions).await
+    }
+
+    async fn recent_personal_bests(&self, limit: i64) -> Result<Vec<RecentPersonalBestRow>> {
+        self.storage.recent_personal_bests(limit).await
+    }
+
+    async fn records(&self, conditions: &ConditionsFilter) -> Result<Vec<CategoryRecordRow>> {
+        self.storage.records(conditions).await
+    }
+
+    async fn recent_records(&self, limit: i64) -> Result<Vec<RecordSetRow>> {
+        self.storage.recent_records(limit).await
+    }
+
+    async fn name_track(&self, name: &str, display_name: &str) -> Result<bool> {
+        self.storage.name_track(name, display_name).await
+    }
+
+    async fn name_car(
+        &self,
+        name: &str,
+        display_name: Option<&str>,
+        category: Option<&CarCategory>,
+    ) -> Result<bool> {
+        self.storage.name_car(name, display_name, category).await
+    }
+
+    async fn link_discord_user(&self, driver_name: &str, discord_user_id: &str) -> Result<bool> {
+        self.storage
+            .link_discord_user(driver_name, discord_user_id)
+            .await
+    }
+
+    /// Events are only useful as they happen, so they're dropped rather than queued.
+    async fn publish_event(&self, payload: &str) -> Result<()> {
+        if self.recently_unreachable() {
+            return Ok(());
+        }
+        match self.storage.publish_event(payload).await {
+            Err(error) if is_unreachable(&error) => {
+                self.mark_unreachable();
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    async fn listen_for_events(&self) -> Result<Box<dyn EventListener>> {
+        self.storage.listen_for_events().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::testing::{drive, TempDatabase};
+    use crate::storage::SqliteStorage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A SQLite database that can't be reached once it's run out of calls.
+    struct Unreliable {
+        storage: SqliteStorage,
+        /// For checking what was stored.
+        pool: Pool<Sqlite>,
+        calls_left: AtomicUsize,
+    }
+
+    impl Unreliable {
+        async fn connect(path: &Path) -> Result<Arc<Unreliable>> {
+            let url = format!("sqlite:{}", path.display());
+            Ok(Arc::new(Unreliable {
+                storage: SqliteStorage::connect(&url).await?,
+                pool: Pool::connect(&url).await?,
+                calls_left: AtomicUsize::new(usize::MAX),
+            }))
+        }
+
+        fn go_down_after(&self, calls: usize) {
+            self.calls_left.store(calls, Ordering::SeqCst);
+        }
+
+        fn come_back(&self) {
+            self.go_down_after(usize::MAX);
+        }
+
+        fn call(&self) -> Result<()> {
+            self.calls_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .map(|_| ())
+                .map_err(|_| sqlx::Error::PoolTimedOut.into())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Storage for Unreliable {
+        async fn upsert_driver(&self, name: &str) -> Result<Driver> {
+            self.call()?;
+            self.storage.upsert_driver(name).await
+        }
+
+        async fn upsert_track(&self, name: &str, layout: &TrackLayout) -> Result<TrackRow> {
+            self.call()?;
+            self.storage.upsert_track(name, layout).await
+        }
+
+        async fn upsert_car(&self, name: &str, category: &CarCategory) -> Result<CarRow> {
+            self.call()?;
+            self.storage.upsert_car(name, category).await
+        }
+
+        async fn start_session(&self, session: &NewSession) -> Result<i64> {
+            self.call()?;
+            self.storage.start_session(session).await
+        }
+
+        async fn end_session(&self, session_id: i64, ended_at: DateTime<Utc>) -> Result<()> {
+            self.call()?;
+            self.storage.end_session(session_id, ended_at).await
+        }
+
+        async fn find_session(
+            &self,
+            driver_id: i64,
+            started_at: DateTime<Utc>,
+        ) -> Result<Option<i64>> {
+            self.call()?;
+            self.storage.find_session(driver_id, started_at).await
+        }
+
+        async fn find_lap(&self, driver_id: i64, created_at: DateTime<Utc>) -> Result<Option<i64>> {
+            self.call()?;
+            self.storage.find_lap(driver_id, created_at).await
+        }
+
+        async fn insert_lap(&self, lap: &NewLap) -> Result<i64> {
+            self.call()?;
+            self.storage.insert_lap(lap).await
+        }
+
+        async fn save_best_lap(&self, best_lap: &BestLapData) -> Result<Option<BestLap>> {
+            self.call()?;
+            self.storage.save_best_lap(best_lap).await
+        }
+
+        async fn discord_user_id(&self, driver_id: i64) -> Result<Option<String>> {
+            self.call()?;
+            self.storage.discord_user_id(driver_id).await
+        }
+
+        async fn theoretical_best(
+            &self,
+            driver_id: i64,
+            track_id: i64,
+            car_id: i64,
+            conditions: &ConditionsFilter,
+        ) -> Result<Option<i64>> {
+            self.call()?;
+            self.storage
+                .theoretical_best(driver_id, track_id, car_id, conditions)
+                .await
+        }
+
+        async fn sectors(&self, lap_id: i64) -> Result<Vec<i64>> {
+            self.call()?;
+            self.storage.sectors(lap_id).await
+        }
+
+        async fn leaderboard(
+            &self,
+            track_id: i64,
+            car_ids: Option<&[i64]>,
+            category: Option<&str>,
+            conditions: &ConditionsFilter,
+        ) -> Result<Vec<BestLapWithDriver>> {
+            self.call()?;
+            self.storage
+                .leaderboard(track_id, car_ids, category, conditions)
+                .await
+        }
+
+        async fn tracks(&self) -> Result<Vec<TrackRow>> {
+            self.call()?;
+            self.storage.tracks().await
+        }
+
+        async fn find_track(&self, name: &str, layout: Option<&str>) -> Result<Option<TrackRow>> {
+            self.call()?;
+            self.storage.find_track(name, layout).await
+        }
+
+        async fn cars(&self) -> Result<Vec<CarRow>> {
+            self.call()?;
+            self.storage.cars().await
+        }
+
+        async fn find_car(&self, name: &str) -> Result<Option<CarRow>> {
+            self.call()?;
+            self.storage.find_car(name).await
+        }
+
+        async fn cars_at_track(&self, track_id: i64) -> Result<Vec<CarRow>> {
+            self.call()?;
+            self.storage.cars_at_track(track_id).await
+        }
+
+        async fn categories(&self) -> Result<Vec<String>> {
+            self.call()?;
+            self.storage.categories().await
+        }
+
+        async fn lap_count(&self, driver_name: &str) -> Result<Option<i64>> {
+            self.call()?;
+            self.storage.lap_count(driver_name).await
+        }
+
+        async fn personal_bests(
+            &self,
+            driver_name: &str,
+            conditions: &ConditionsFilter,
+        ) -> Result<Vec<PersonalBestRow>> {
+            self.call()?;
+            self.storage.personal_bests(driver_name, conditions).await
+        }
+
+        async fn recent_personal_bests(&self, limit: i64) -> Result<Vec<RecentPersonalBestRow>> {
+            self.call()?;
+            self.storage.recent_personal_bests(limit).await
+        }
+
+        async fn records(&self, conditions: &ConditionsFilter) -> Result<Vec<CategoryRecordRow>> {
+            self.call()?;
+            self.storage.records(conditions).await
+        }
+
+        async fn recent_records(&self, limit: i64) -> Result<Vec<RecordSetRow>> {
+            self.call()?;
+            self.storage.recent_records(limit).await
+        }
+
+        async fn name_track(&self, name: &str, display_name: &str) -> Result<bool> {
+            self.call()?;
+            self.storage.name_track(name, display_name).await
+        }
+
+        async fn name_car(
+            &self,
+            name: &str,
+            display_name: Option<&str>,
+            category: Option<&CarCategory>,
+        ) -> Result<bool> {
+            self.call()?;
+            self.storage.name_car(name, display_name, category).await
+        }
+
+        async fn link_discord_user(
+            &self,
+            driver_name: &str,
+            discord_user_id: &str,
+        ) -> Result<bool> {
+            self.call()?;
+            self.storage
+                .link_discord_user(driver_name, discord_user_id)
+                .await
+        }
+
+        async fn publish_event(&self, payload: &str) -> Result<()> {
+            self.call()?;
+            self.storage.publish_event(payload).await
+        }
+
+        async fn listen_for_events(&self) -> Result<Box<dyn EventListener>> {
+            self.call()?;
+            self.storage.listen_for_events().await
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_writes_are_replayed_in_order_with_their_real_ids() -> Result<()> {
+        let database_file = TempDatabase::new("replay_in_order_database");
+        let queue_file = TempDatabase::new("replay_in_order_queue");
+        let database = Unreliable::connect(&database_file.path).await?;
+        database.go_down_after(0);
+        let queued = QueuedStorage::open(database.clone(), &queue_file.path).await?;
+
+        let (lap, lap_id) = drive(&queued, "Alice", "", 130000).await?;
+        let placeholders = [
+            lap.driver_id,
+            lap.track_id,
+            lap.car_id,
+            lap.session_id,
+            lap_id,
+        ];
+        assert!(placeholders.iter().all(|&id| id < 0));
+
+        database.come_back();
+        *queued.unreachable_at.lock().unwrap() = None;
+        // The queue is replayed before the next write is made.
+        let bob = queued.upsert_driver("Bob").await?;
+        let queued_writes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM queued_write")
+            .fetch_one(&queued.queue)
+            .await?;
+        assert_eq!(queued_writes, 0);
+
+        let real_lap_id = queued.resolve(lap_id).await;
+        assert!(real_lap_id > 0);
+        let replayed_lap_id: i64 =
+            sqlx::query_scalar("SELECT id FROM replayed_id WHERE placeholder = ?1")
+                .bind(lap_id)
+                .fetch_one(&queued.queue)
+                .await?;
+        assert_eq!(replayed_lap_id, real_lap_id);
+
+        let (driver, session_id, lap_time_ms): (String, i64, i64) = sqlx::query_as(
+            "SELECT d.name, l.session_id, l.lap_time_ms FROM lap l JOIN driver d ON d.id = l.driver_id WHERE l.id = ?1",
+        )
+        .bind(real_lap_id)
+        .fetch_one(&database.pool)
+        .await?;
+        assert_eq!(driver, "Alice");
+        assert_eq!(session_id, queued.resolve(lap.session_id).await);
+        assert_eq!(lap_time_ms, 130000);
+        assert_eq!(
+            database.storage.sectors(real_lap_id).await?,
+            vec![40000, 50000, 40000]
+        );
+        let ended: bool =
+            sqlx::query_scalar("SELECT ended_at IS NOT NULL FROM session WHERE id = ?1")
+                .bind(session_id)
+                .fetch_one(&database.pool)
+                .await?;
+        assert!(ended);
+        // Alice was queued first, so she reached the database before Bob.
+        assert!(queued.resolve(lap.driver_id).await < bob.id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn a_queued_best_lap_never_replaces_a_faster_one() -> Result<()> {
+        let database_file = TempDatabase::new("faster_best_lap_database");
+        let queue_file = TempDatabase::new("faster_best_lap_queue");
+        let database = Unreliable::connect(&database_file.path).await?;
+        database.go_down_after(0);
+        let queued = QueuedStorage::open(database.clone(), &queue_file.path).await?;
+        drive(&queued, "Alice", "", 132000).await?;
+        drop(queued);
+
+        // A faster lap is stored from somewhere else while the tracker is offline.
+        drive(&database.storage, "Alice", "", 129000).await?;
+
+        database.come_back();
+        // Reopening replays whatever was left queued.
+        QueuedStorage::open(database.clone(), &queue_file.path).await?;
+        let laps: Vec<i64> = sqlx::query_scalar("SELECT lap_time_ms FROM lap ORDER BY id")
+            .fetch_all(&database.pool)
+            .await?;
+        assert_eq!(laps, vec![129000, 132000]);
+        let best_laps: Vec<i64> = sqlx::query_scalar("SELECT lap_time_ms FROM best_lap")
+            .fetch_all(&database.pool)
+            .await?;
+        assert_eq!(best_laps, vec![129000]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replay_resumes_after_a_restart_part_way_through() -> Result<()> {
+        let database_file = TempDatabase::new("resume_replay_database");
+        let queue_file = TempDatabase::new("resume_replay_queue");
+        let database = Unreliable::connect(&database_file.path).await?;
+        database.go_down_after(0);
+        let queued = QueuedStorage::open(database.clone(), &queue_file.path).await?;
+        let (lap, _) = drive(&queued, "Alice", "", 130000).await?;
+
+        // The driver and track are replayed before the database goes again, and the tracker is
+        // restarted while it's away.
+        database.go_down_after(2);
+        *queued.unreachable_at.lock().unwrap() = None;
+        queued.upsert_driver("Bob").await?;
+        drop(queued);
+        let queued = QueuedStorage::open(database.clone(), &queue_file.path).await?;
+        assert!(queued.resolve(lap.driver_id).await > 0);
+        assert!(queued.resolve(lap.track_id).await > 0);
+        assert!(queued.resolve(lap.car_id).await < 0);
+        drop(queued);
+
+        // The rest refer to the driver and track by the placeholders replayed before the restart.
+        database.come_back();
+        QueuedStorage::open(database.clone(), &queue_file.path).await?;
+        let laps: Vec<(String, String, i64)> = sqlx::query_as(
+            "SELECT d.name, t.name, l.lap_time_ms FROM lap l JOIN driver d ON d.id = l.driver_id JOIN track t ON t.id = l.track_id",
+        )
+        .fetch_all(&database.pool)
+        .await?;
+        assert_eq!(laps, vec![("Alice".to_string(), "spa".to_string(), 130000)]);
+        let drivers: Vec<String> = sqlx::query_scalar("SELECT name FROM driver ORDER BY id")
+            .fetch_all(&database.pool)
+            .await?;
+        assert_eq!(drivers, vec!["Alice", "Bob"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,672 @@
+use super::{EventListener, NewLap, NewSession, Storage, TrackLayout};
+use crate::cars::CarCategory;
+use crate::session::{ConditionsFilter, GripStatus, RainLevel, SessionType, Surface};
+use crate::{
+    BestLap, BestLapData, BestLapWithDriver, CarRow, CategoryRecordRow, Driver, PersonalBestRow,
+    RecentPersonalBestRow, RecordSetRow, TrackRow,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{ConnectOptions, Connection, Pool, Sqlite};
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How often `serve` checks for events published by trackers.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Keeps everything in a local SQLite file, for driving without a database server. Queries are
+/// checked at runtime, as the query macros are checked against Postgres.
+pub(crate) struct SqliteStorage {
+    pool: Pool<Sqlite>,
+}
+
+impl SqliteStorage {
+    pub(crate) async fn connect(url: &str) -> Result<SqliteStorage> {
+        // The tracker and `serve` can share the file.
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        // Changing a constraint means rebuilding the table, which SQLite only allows with foreign
+        // keys off, so migrations get a connection of their own.
+        let mut connection = options.clone().foreign_keys(false).connect().await?;
+        sqlx::migrate!("migrations/sqlite")
+            .run(&mut connection)
+            .await?;
+        connection.close().await?;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+        Ok(SqliteStorage { pool })
+    }
+
+    /// Every session, oldest first, with what it refers to by name, for copying elsewhere.
+    pub(crate) async fn export_sessions(&self) -> Result<Vec<ExportedSession>> {
+        Ok(sqlx::query_as(
+            "SELECT s.id, d.name AS driver_name, t.name AS track_name, t.layout, t.length_km, t.sector_count, t.country, c.name AS car_name, c.category, s.session_index, s.session_type, s.air_temperature, s.track_temperature, s.rain_level, s.grip_status, s.started_at, s.ended_at FROM session s JOIN driver d ON d.id = s.driver_id JOIN track t ON t.id = s.track_id JOIN car c ON c.id = s.car_id ORDER BY s.started_at, s.id",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// A session's laps in the order they were completed.
+    pub(crate) async fn export_laps(&self, session_id: i64) -> Result<Vec<ExportedLap>> {
+        Ok(sqlx::query_as(
+            "SELECT l.id, l.lap_number, l.lap_time_ms, l.is_valid, l.invalid_reason, l.surface, l.track_temperature, l.created_at, l.game_version, l.bop_era, EXISTS (SELECT 1 FROM best_lap b WHERE b.lap_id = l.id) AS is_best_lap FROM lap l WHERE l.session_id = ?1 ORDER BY l.created_at, l.id",
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct ExportedSession {
+    pub(crate) id: i64,
+    pub(crate) driver_name: String,
+    pub(crate) track_name: String,
+    pub(crate) layout: String,
+    pub(crate) length_km: Option<f64>,
+    pub(crate) sector_count: Option<i32>,
+    pub(crate) country: Option<String>,
+    pub(crate) car_name: String,
+    pub(crate) category: String,
+    pub(crate) session_index: i32,
+    pub(crate) session_type: SessionType,
+    pub(crate) air_temperature: f32,
+    pub(crate) track_temperature: f32,
+    pub(crate) rain_level: RainLevel,
+    pub(crate) grip_status: GripStatus,
+    pub(crate) started_at: DateTime<Utc>,
+    pub(crate) ended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct ExportedLap {
+    pub(crate) id: i64,
+    pub(crate) lap_number: i32,
+    pub(crate) lap_time_ms: i64,
+    pub(crate) is_valid: bool,
+    pub(crate) invalid_reason: Option<String>,
+    pub(crate) surface: Surface,
+    pub(crate) track_temperature: f32,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) game_version: Option<String>,
+    pub(crate) bop_era: String,
+    /// Whether it's the driver's best lap in its car, surface and era.
+    pub(crate) is_best_lap: bool,
+}
+
+#[async_trait::async_trait]
+impl Storage for SqliteStorage {
+    async fn upsert_driver(&self, name: &str) -> Result<Driver> {
+        Ok(sqlx::query_as(
+            "INSERT INTO driver (name) VALUES (?1) ON CONFLICT (name) DO UPDATE set name=?1 RETURNING id, name",
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn upsert_track(&self, name: &str, layout: &TrackLayout) -> Result<TrackRow> {
+        Ok(sqlx::query_as(
+            "INSERT INTO track (name, layout, length_km, sector_count, country) VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT (name, layout) DO UPDATE set length_km = COALESCE(?3, track.length_km), sector_count = COALESCE(?4, track.sector_count), country = COALESCE(?5, track.country) RETURNING id, name, display_name, layout, length_km, sector_count, country",
+        )
+        .bind(name)
+        .bind(&layout.layout)
+        .bind(layout.length_km)
+        .bind(layout.sector_count)
+        .bind(&layout.country)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn upsert_car(&self, name: &str, category: &CarCategory) -> Result<CarRow> {
+        Ok(sqlx::query_as(
+            "INSERT INTO car (name, category) VALUES (?1, ?2) ON CONFLICT (name) DO UPDATE set category = CASE WHEN car.category = 'UNKNOWN' THEN excluded.category ELSE car.category END RETURNING id, name, category, display_name",
+        )
+        .bind(name)
+        .bind(category.to_string())
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn start_session(&self, session: &NewSession) -> Result<i64> {
+        Ok(sqlx::query_scalar(
+            "INSERT INTO session (driver_id, track_id, car_id, session_index, session_type, air_temperature, track_temperature, rain_level, grip_status, started_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10) RETURNING id",
+        )
+        .bind(session.driver_id)
+        .bind(session.track_id)
+        .bind(session.car_id)
+        .bind(session.session_index)
+        .bind(session.session_type)
+        .bind(session.conditions.air_temperature)
+        .bind(session.conditions.track_temperature)
+        .bind(session.conditions.rain_level)
+        .bind(session.conditions.grip_status)
+        .bind(session.started_at)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn end_session(&self, session_id: i64, ended_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE session SET ended_at = ?2 WHERE id = ?1")
+            .bind(session_id)
+            .bind(ended_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn find_session(&self, driver_id: i64, started_at: DateTime<Utc>) -> Result<Option<i64>> {
+        Ok(
+            sqlx::query_scalar("SELECT id FROM session WHERE driver_id = ?1 AND started_at = ?2")
+                .bind(driver_id)
+                .bind(started_at)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn find_lap(&self, driver_id: i64, created_at: DateTime<Utc>) -> Result<Option<i64>> {
+        Ok(
+            sqlx::query_scalar("SELECT id FROM lap WHERE driver_id = ?1 AND created_at = ?2")
+                .bind(driver_id)
+                .bind(created_at)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn insert_lap(&self, lap: &NewLap) -> Result<i64> {
+        // The lap and its sectors are stored together, so a lap is never left without them.
+        let mut transaction = self.pool.begin().await?;
+        let lap_id: i64 = sqlx::query_scalar(
+            "INSERT INTO lap (driver_id, track_id, car_id, session_id, lap_number, lap_time_ms, is_valid, invalid_reason, surface, track_temperature, created_at, game_version, bop_era) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13) RETURNING id",
+        )
+        .bind(lap.driver_id)
+        .bind(lap.track_id)
+        .bind(lap.car_id)
+        .bind(lap.session_id)
+        .bind(lap.lap_number)
+        .bind(lap.lap_time_ms)
+        .bind(lap.is_valid)
+        .bind(&lap.invalid_reason)
+        .bind(lap.surface)
+        .bind(lap.track_temperature)
+        .bind(lap.created_at)
+        .bind(&lap.game_version)
+        .bind(&lap.bop_era)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        for (sector_index, sector_time_ms) in lap.sectors_ms.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO lap_sector (lap_id, sector_index, sector_time_ms) VALUES (?1, ?2, ?3)",
+            )
+            .bind(lap_id)
+            .bind(sector_index as i32)
+            .bind(sector_time_ms)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(lap_id)
+    }
+
+    async fn save_best_lap(&self, best_lap: &BestLapData) -> Result<Option<BestLap>> {
+        Ok(sqlx::query_as(
+            "INSERT INTO best_lap (driver_id, track_id, car_id, created_at, lap_time_ms, lap_id, surface, bop_era) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) ON CONFLICT (driver_id, track_id, car_id, surface, bop_era) DO UPDATE set lap_time_ms=?5, lap_id=?6, created_at=?4 WHERE best_lap.lap_time_ms > ?5 RETURNING id, driver_id, track_id, created_at, lap_time_ms, car_id, lap_id, surface, bop_era",
+        )
+        .bind(best_lap.driver_id)
+        .bind(best_lap.track_id)
+        .bind(best_lap.car_id)
+        .bind(best_lap.created_at)
+        .bind(best_lap.lap_time_ms)
+        .bind(best_lap.lap_id)
+        .bind(best_lap.surface)
+        .bind(&best_lap.bop_era)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn discord_user_id(&self, driver_id: i64) -> Result<Option<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT discord_user_id FROM driver_discord_user WHERE driver_id = ?1",
+        )
+        .bind(driver_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn theoretical_best(
+        &self,
+        driver_id: i64,
+        track_id: i64,
+        car_id: i64,
+        conditions: &ConditionsFilter,
+    ) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar(
+            r#"SELECT SUM(best_sector)
+       FROM (SELECT MIN(lap_sector.sector_time_ms) as best_sector
+             from lap_sector
+                      INNER JOIN lap l on l.id = lap_sector.lap_id
+             WHERE l.driver_id = ?1 AND l.track_id = ?2 AND l.car_id = ?3 AND l.is_valid
+               AND l.surface = ?4
+               AND (?5 IS NULL OR l.track_temperature >= ?5)
+               AND (?6 IS NULL OR l.track_temperature < ?6)
+               AND (?7 IS NULL OR l.bop_era = ?7)
+             GROUP BY lap_sector.sector_index) best_sectors"#,
+        )
+        .bind(driver_id)
+        .bind(track_id)
+        .bind(car_id)
+        .bind(conditions.surface)
+        .bind(conditions.track_temperature.map(|(low, _)| low))
+        .bind(conditions.track_temperature.map(|(_, high)| high))
+        .bind(&conditions.bop_era)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn sectors(&self, lap_id: i64) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar(
+            "SELECT sector_time_ms FROM lap_sector WHERE lap_id = ?1 ORDER BY sector_index ASC",
+        )
+        .bind(lap_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn leaderboard(
+        &self,
+        track_id: i64,
+        car_ids: Option<&[i64]>,
+        category: Option<&str>,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<BestLapWithDriver>> {
+        // SQLite has no arrays, so the cars are passed as a JSON one.
+        let car_ids = car_ids.map(serde_json::to_string).transpose()?;
+        Ok(sqlx::query_as(
+            r#"SELECT id, track_id, driver_id, lap_time_ms, created_at, car_id, driver_name, car_name, car_display_name
+       FROM (SELECT lap.id,
+                    lap.track_id,
+                    lap.driver_id,
+                    lap.lap_time_ms,
+                    lap.created_at,
+                    lap.car_id,
+                    d.name as driver_name,
+                    c.name as car_name,
+                    c.display_name as car_display_name,
+                    ROW_NUMBER() OVER (PARTITION BY lap.driver_id ORDER BY lap.lap_time_ms, lap.created_at) as place
+             from lap
+                      INNER JOIN driver d on d.id = lap.driver_id
+                      INNER JOIN car c on c.id = lap.car_id
+             WHERE lap.is_valid AND lap.track_id = ?1
+               AND (?2 IS NULL OR c.id IN (SELECT value FROM json_each(?2)))
+               AND (?3 IS NULL OR c.category = ?3)
+               AND lap.surface = ?4
+               AND (?5 IS NULL OR lap.track_temperature >= ?5)
+               AND (?6 IS NULL OR lap.track_temperature < ?6)
+               AND (?7 IS NULL OR lap.bop_era = ?7)) best
+       WHERE place = 1
+       ORDER BY lap_time_ms ASC, created_at ASC"#,
+        )
+        .bind(track_id)
+        .bind(car_ids)
+        .bind(category)
+        .bind(conditions.surface)
+        .bind(conditions.track_temperature.map(|(low, _)| low))
+        .bind(conditions.track_temperature.map(|(_, high)| high))
+        .bind(&conditions.bop_era)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn tracks(&self) -> Result<Vec<TrackRow>> {
+        Ok(
+            sqlx::query_as(
+            "SELECT id, name, display_name, layout, length_km, sector_count, country FROM track ORDER BY name, id",
+        )
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn find_track(&self, name: &str, layout: Option<&str>) -> Result<Option<TrackRow>> {
+        Ok(sqlx::query_as(
+            "SELECT id, name, display_name, layout, length_km, sector_count, country FROM track WHERE name = ?1 AND (?2 IS NULL OR layout = ?2) ORDER BY id DESC LIMIT 1",
+        )
+        .bind(name)
+        .bind(layout)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn cars(&self) -> Result<Vec<CarRow>> {
+        Ok(sqlx::query_as(
+            "SELECT id, name, category, display_name FROM car ORDER BY category, name",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn find_car(&self, name: &str) -> Result<Option<CarRow>> {
+        Ok(
+            sqlx::query_as("SELECT id, name, category, display_name FROM car WHERE name = ?1")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn cars_at_track(&self, track_id: i64) -> Result<Vec<CarRow>> {
+        Ok(sqlx::query_as(
+            r#"SELECT DISTINCT c.id, c.name, c.category, c.display_name
+       FROM lap l
+                INNER JOIN car c on c.id = l.car_id
+       WHERE l.track_id = ?1
+       ORDER BY 3, 2"#,
+        )
+        .bind(track_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn categories(&self) -> Result<Vec<String>> {
+        Ok(
+            sqlx::query_scalar("SELECT DISTINCT category FROM car ORDER BY 1")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn lap_count(&self, driver_name: &str) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar(
+            "SELECT COUNT(l.id) FROM driver d LEFT JOIN lap l on l.driver_id = d.id WHERE d.name = ?1 GROUP BY d.id",
+        )
+        .bind(driver_name)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn personal_bests(
+        &self,
+        driver_name: &str,
+        conditions: &ConditionsFilter,
+    ) -> Result<Vec<PersonalBestRow>> {
+        Ok(sqlx::query_as(
+            r#"SELECT track_name, track_display_name, track_layout, car_name, car_display_name, category, lap_time_ms, created_at
+       FROM (SELECT t.name as track_name,
+                    t.display_name as track_display_name,
+                    t.layout as track_layout,
+                    c.name as car_name,
+                    c.display_name as car_display_name,
+                    c.category,
+                    l.lap_time_ms,
+                    l.created_at,
+                    ROW_NUMBER() OVER (PARTITION BY t.id, c.name ORDER BY l.lap_time_ms, l.created_at) as place
+             FROM lap l
+                      INNER JOIN driver d on d.id = l.driver_id
+                      INNER JOIN track t on t.id = l.track_id
+                      INNER JOIN car c on c.id = l.car_id
+             WHERE d.name = ?1 AND l.is_valid AND l.surface = ?2
+               AND (?3 IS NULL OR l.track_temperature >= ?3)
+               AND (?4 IS NULL OR l.track_temperature < ?4)
+               AND (?5 IS NULL OR l.bop_era = ?5)) best
+       WHERE place = 1
+       ORDER BY track_name, track_layout, car_name"#,
+        )
+        .bind(driver_name)
+        .bind(conditions.surface)
+        .bind(conditions.track_temperature.map(|(low, _)| low))
+        .bind(conditions.track_temperature.map(|(_, high)| high))
+        .bind(&conditions.bop_era)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn recent_personal_bests(&self, limit: i64) -> Result<Vec<RecentPersonalBestRow>> {
+        Ok(sqlx::query_as(
+            r#"SELECT d.name as driver_name,
+       t.name as track_name,
+       t.display_name as track_display_name,
+       t.layout as track_layout,
+       c.name as car_name,
+       c.display_name as car_display_name,
+       c.category,
+       best_lap.surface,
+       best_lap.lap_time_ms,
+       best_lap.created_at
+       FROM best_lap
+                INNER JOIN driver d on d.id = best_lap.driver_id
+                INNER JOIN track t on t.id = best_lap.track_id
+                INNER JOIN car c on c.id = best_lap.car_id
+       ORDER BY best_lap.created_at DESC
+       LIMIT ?1"#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn records(&self, conditions: &ConditionsFilter) -> Result<Vec<CategoryRecordRow>> {
+        Ok(sqlx::query_as(
+            r#"SELECT track_name, track_display_name, track_layout, category, driver_name, car_name, car_display_name, lap_time_ms, created_at
+       FROM (SELECT t.name as track_name,
+                    t.display_name as track_display_name,
+                    t.layout as track_layout,
+                    c.category,
+                    d.name as driver_name,
+                    c.name as car_name,
+                    c.display_name as car_display_name,
+                    l.lap_time_ms,
+                    l.created_at,
+                    ROW_NUMBER() OVER (PARTITION BY t.id, c.category ORDER BY l.lap_time_ms, l.created_at) as place
+             FROM lap l
+                      INNER JOIN driver d on d.id = l.driver_id
+                      INNER JOIN track t on t.id = l.track_id
+                      INNER JOIN car c on c.id = l.car_id
+             WHERE l.is_valid AND l.surface = ?1
+               AND (?2 IS NULL OR l.track_temperature >= ?2)
+               AND (?3 IS NULL OR l.track_temperature < ?3)
+               AND (?4 IS NULL OR l.bop_era = ?4)) best
+       WHERE place = 1
+       ORDER BY track_name, track_layout, category"#,
+        )
+        .bind(conditions.surface)
+        .bind(conditions.track_temperature.map(|(low, _)| low))
+        .bind(conditions.track_temperature.map(|(_, high)| high))
+        .bind(&conditions.bop_era)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn recent_records(&self, limit: i64) -> Result<Vec<RecordSetRow>> {
+        Ok(sqlx::query_as(
+            r#"SELECT t.name as track_name,
+       t.display_name as track_display_name,
+       t.layout as track_layout,
+       c.category,
+       l.surface,
+       d.name as driver_name,
+       c.name as car_name,
+       c.display_name as car_display_name,
+       l.lap_time_ms,
+       l.created_at
+       FROM lap l
+                INNER JOIN driver d on d.id = l.driver_id
+                INNER JOIN track t on t.id = l.track_id
+                INNER JOIN car c on c.id = l.car_id
+       WHERE l.is_valid
+         AND NOT EXISTS (SELECT 1
+                         FROM lap earlier
+                                  INNER JOIN car ec on ec.id = earlier.car_id
+                         WHERE earlier.is_valid
+                           AND earlier.track_id = l.track_id
+                           AND earlier.surface = l.surface
+                           AND earlier.bop_era = l.bop_era
+                           AND ec.category = c.category
+                           AND earlier.lap_time_ms <= l.lap_time_ms
+                           AND earlier.created_at < l.created_at)
+       ORDER BY l.created_at DESC
+       LIMIT ?1"#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn name_track(&self, name: &str, display_name: &str) -> Result<bool> {
+        Ok(
+            sqlx::query("UPDATE track SET display_name = ?2 WHERE name = ?1")
+                .bind(name)
+                .bind(display_name)
+                .execute(&self.pool)
+                .await?
+                .rows_affected()
+                > 0,
+        )
+    }
+
+    async fn name_car(
+        &self,
+        name: &str,
+        display_name: Option<&str>,
+        category: Option<&CarCategory>,
+    ) -> Result<bool> {
+        Ok(sqlx::query(
+            "UPDATE car SET display_name = COALESCE(?2, display_name), category = COALESCE(?3, category) WHERE name = ?1",
+        )
+        .bind(name)
+        .bind(display_name)
+        .bind(category.map(|category| category.to_string()))
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+
+    async fn link_discord_user(&self, driver_name: &str, discord_user_id: &str) -> Result<bool> {
+        Ok(sqlx::query(
+            "INSERT INTO driver_discord_user (driver_id, discord_user_id) SELECT id, ?2 FROM driver WHERE name = ?1 ON CONFLICT (driver_id) DO UPDATE SET discord_user_id = excluded.discord_user_id",
+        )
+        .bind(driver_name)
+        .bind(discord_user_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0)
+    }
+
+    async fn publish_event(&self, payload: &str) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("INSERT INTO event (payload) VALUES (?1)")
+            .bind(payload)
+            .execute(&mut *transaction)
+            .await?;
+        // Events are only useful as they happen, so old ones are dropped as new ones arrive.
+        sqlx::query("DELETE FROM event WHERE created_at < datetime('now', '-1 minute')")
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn listen_for_events(&self) -> Result<Box<dyn EventListener>> {
+        let last_id = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM event")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(Box::new(SqliteEventListener {
+            pool: self.pool.clone(),
+            last_id,
+            pending: VecDeque::new(),
+        }))
+    }
+}
+
+/// Polls the event table for events published since it started listening.
+struct SqliteEventListener {
+    pool: Pool<Sqlite>,
+    last_id: i64,
+    pending: VecDeque<String>,
+}
+
+#[async_trait::async_trait]
+impl EventListener for SqliteEventListener {
+    async fn recv(&mut self) -> Result<String> {
+        loop {
+            if let Some(payload) = self.pending.pop_front() {
+                return Ok(payload);
+            }
+            let events: Vec<(i64, String)> =
+                sqlx::query_as("SELECT id, payload FROM event WHERE id > ?1 ORDER BY id")
+                    .bind(self.last_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+            match events.last() {
+                Some((id, _)) => self.last_id = *id,
+                None => tokio::time::sleep(EVENT_POLL_INTERVAL).await,
+            }
+            self.pending
+                .extend(events.into_iter().map(|(_, payload)| payload));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Surface;
+    use crate::storage::testing::{drive, TempDatabase};
+
+    #[tokio::test]
+    async fn laps_recorded_before_layouts_stay_off_a_new_layouts_leaderboard() -> Result<()> {
+        let file = TempDatabase::new("earlier_layout");
+        let storage = SqliteStorage::connect(&file.url()).await?;
+        drive(&storage, "Alice", "", 130000).await?;
+
+        let layout = TrackLayout {
+            layout: "2024".to_string(),
+            ..TrackLayout::default()
+        };
+        let track = storage.upsert_track("spa", &layout).await?;
+        let conditions = ConditionsFilter {
+            surface: Surface::Dry,
+            track_temperature: None,
+            bop_era: None,
+        };
+        assert!(storage
+            .leaderboard(track.id, None, None, &conditions)
+            .await?
+            .is_empty());
+
+        let tracks = storage.tracks().await?;
+        let earlier = tracks.iter().find(|t| t.layout.is_empty()).unwrap();
+        assert_ne!(earlier.id, track.id);
+        let leaderboard = storage
+            .leaderboard(earlier.id, None, None, &conditions)
+            .await?;
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].driver_name, "Alice");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn linking_a_discord_user_replaces_the_last_one() -> Result<()> {
+        let file = TempDatabase::new("discord_user");
+        let storage = SqliteStorage::connect(&file.url()).await?;
+        assert!(!storage.link_discord_user("Alice", "1").await?);
+        let alice = storage.upsert_driver("Alice").await?;
+
+        assert!(storage.link_discord_user("Alice", "1").await?);
+        assert!(storage.link_discord_user("Alice", "2").await?);
+        assert_eq!(
+            storage.discord_user_id(alice.id).await?.as_deref(),
+            Some("2")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use super::{NewLap, NewSession, Storage, TrackLayout};
+use crate::cars::CarCategory;
+use crate::session::{Conditions, GripStatus, RainLevel, SessionType, Surface};
+use crate::BestLapData;
+use anyhow::Result;
+use chrono::Utc;
+use std::path::PathBuf;
+
+/// A SQLite file in the temp directory, removed once the test using it is done.
+pub(crate) struct TempDatabase {
+    pub(crate) path: PathBuf,
+}
+
+impl TempDatabase {
+    /// `name` has to be unique across tests, as they run at the same time.
+    pub(crate) fn new(name: &str) -> TempDatabase {
+        TempDatabase {
+            path: std::env::temp_dir()
+                .join(format!("sim_leaderboards_{name}_{}.db", std::process::id())),
+        }
+    }
+
+    pub(crate) fn url(&self) -> String {
+        format!("sqlite:{}", self.path.display())
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", self.path.display()));
+        }
+    }
+}
+
+pub(crate) fn new_session(driver_id: i64, track_id: i64, car_id: i64) -> NewSession {
+    NewSession {
+        driver_id,
+        track_id,
+        car_id,
+        session_index: 0,
+        session_type: SessionType::Practice,
+        conditions: Conditions {
+            air_temperature: 21.5,
+            track_temperature: 28.0,
+            rain_level: RainLevel::NoRain,
+            grip_status: GripStatus::Optimum,
+        },
+        started_at: Utc::now(),
+    }
+}
+
+pub(crate) fn new_lap(session: &NewSession, session_id: i64, lap_time_ms: i64) -> NewLap {
+    NewLap {
+        driver_id: session.driver_id,
+        track_id: session.track_id,
+        car_id: session.car_id,
+        session_id,
+        lap_number: 1,
+        lap_time_ms,
+        is_valid: true,
+        invalid_reason: None,
+        surface: Surface::Dry,
+        track_temperature: 28.0,
+        sectors_ms: vec![40000, 50000, lap_time_ms - 90000],
+        created_at: Utc::now(),
+        game_version: Some("1.10.2".to_string()),
+        bop_era: "2024".to_string(),
+    }
+}
+
+pub(crate) fn best_lap(lap: &NewLap, lap_id: i64) -> BestLapData {
+    BestLapData {
+        driver_id: lap.driver_id,
+        track_id: lap.track_id,
+        created_at: lap.created_at,
+        lap_time_ms: lap.lap_time_ms,
+        car_id: lap.car_id,
+        lap_id,
+        surface: lap.surface,
+        bop_era: lap.bop_era.clone(),
+    }
+}
+
+/// Stores a session at Spa with one lap, saved as the driver's best unless they've been faster,
+/// returning the lap and its id.
+pub(crate) async fn drive(
+    storage: &dyn Storage,
+    driver: &str,
+    layout: &str,
+    lap_time_ms: i64,
+) -> Result<(NewLap, i64)> {
+    let driver = storage.upsert_driver(driver).await?;
+    let layout = TrackLayout {
+        layout: layout.to_string(),
+        ..TrackLayout::default()
+    };
+    let track = storage.upsert_track("spa", &layout).await?;
+    let car = storage.upsert_car("bmw_m4_gt3", &CarCategory::GT3).await?;
+    let session = new_session(driver.id, track.id, car.id);
+    let session_id = storage.start_session(&session).await?;
+    let lap = new_lap(&session, session_id, lap_time_ms);
+    let lap_id = storage.insert_lap(&lap).await?;
+    storage.save_best_lap(&best_lap(&lap, lap_id)).await?;
+    storage.end_session(session_id, Utc::now()).await?;
+    Ok((lap, lap_id))
+}
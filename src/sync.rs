@@ -0,0 +1,206 @@
+use crate::cars::CarCategory;
+use crate::session::Conditions;
+use crate::storage::{
+    ExportedLap, ExportedSession, NewLap, NewSession, SqliteStorage, Storage, TrackLayout,
+};
+use crate::BestLapData;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+/// What a sync copied.
+#[derive(Default)]
+pub(crate) struct Synced {
+    pub(crate) sessions: usize,
+    pub(crate) laps: usize,
+    pub(crate) best_laps: usize,
+}
+
+/// Copies the drivers, sessions, laps and best laps in a SQLite file into `to`. Sessions and laps
+/// already copied are skipped, so it can be run again as more laps are driven. Best laps go
+/// through [`Storage::save_best_lap`], so they never replace a faster one already in `to`.
+pub(crate) async fn sync(from: &SqliteStorage, to: &dyn Storage) -> Result<Synced> {
+    let mut synced = Synced::default();
+    // The ids rows were given in `to`, by their names in `from`.
+    let mut drivers = HashMap::new();
+    let mut tracks = HashMap::new();
+    let mut cars = HashMap::new();
+
+    for session in from.export_sessions().await? {
+        let driver_id = match drivers.get(&session.driver_name) {
+            Some(&id) => id,
+            None => {
+                let id = to.upsert_driver(&session.driver_name).await?.id;
+                drivers.insert(session.driver_name.clone(), id);
+                id
+            }
+        };
+        let track_key = (session.track_name.clone(), session.layout.clone());
+        let track_id = match tracks.get(&track_key) {
+            Some(&id) => id,
+            None => {
+                let layout = TrackLayout {
+                    layout: session.layout.clone(),
+                    length_km: session.length_km,
+                    sector_count: session.sector_count,
+                    country: session.country.clone(),
+                };
+                let id = to.upsert_track(&session.track_name, &layout).await?.id;
+                tracks.insert(track_key, id);
+                id
+            }
+        };
+        let car_id = match cars.get(&session.car_name) {
+            Some(&id) => id,
+            None => {
+                let category: CarCategory = session
+                    .category
+                    .parse()
+                    .map_err(|_| anyhow!("Unknown category {}", session.category))?;
+                let id = to.upsert_car(&session.car_name, &category).await?.id;
+                cars.insert(session.car_name.clone(), id);
+                id
+            }
+        };
+
+        let session_id = match to.find_session(driver_id, session.started_at).await? {
+            Some(id) => id,
+            None => {
+                synced.sessions += 1;
+                to.start_session(&new_session(&session, driver_id, track_id, car_id))
+                    .await?
+            }
+        };
+        for lap in from.export_laps(session.id).await? {
+            let lap_id = match to.find_lap(driver_id, lap.created_at).await? {
+                Some(id) => id,
+                None => {
+                    synced.laps += 1;
+                    let sectors_ms = from.sectors(lap.id).await?;
+                    to.insert_lap(&new_lap(
+                        &lap, driver_id, track_id, car_id, session_id, sectors_ms,
+                    ))
+                    .await?
+                }
+            };
+            if lap.is_best_lap {
+                let best_lap = BestLapData {
+                    driver_id,
+                    track_id,
+                    created_at: lap.created_at,
+                    lap_time_ms: lap.lap_time_ms,
+                    car_id,
+                    lap_id,
+                    surface: lap.surface,
+                    bop_era: lap.bop_era.clone(),
+                };
+                if to.save_best_lap(&best_lap).await?.is_some() {
+                    synced.best_laps += 1;
+                }
+            }
+        }
+        // Sessions still being driven when they were last copied may have ended since.
+        if let Some(ended_at) = session.ended_at {
+            to.end_session(session_id, ended_at).await?;
+        }
+    }
+    Ok(synced)
+}
+
+fn new_session(
+    session: &ExportedSession,
+    driver_id: i64,
+    track_id: i64,
+    car_id: i64,
+) -> NewSession {
+    NewSession {
+        driver_id,
+        track_id,
+        car_id,
+        session_index: session.session_index,
+        session_type: session.session_type,
+        conditions: Conditions {
+            air_temperature: session.air_temperature,
+            track_temperature: session.track_temperature,
+            rain_level: session.rain_level,
+            grip_status: session.grip_status,
+        },
+        started_at: session.started_at,
+    }
+}
+
+fn new_lap(
+    lap: &ExportedLap,
+    driver_id: i64,
+    track_id: i64,
+    car_id: i64,
+    session_id: i64,
+    sectors_ms: Vec<i64>,
+) -> NewLap {
+    NewLap {
+        driver_id,
+        track_id,
+        car_id,
+        session_id,
+        lap_number: lap.lap_number,
+        lap_time_ms: lap.lap_time_ms,
+        is_valid: lap.is_valid,
+        invalid_reason: lap.invalid_reason.clone(),
+        surface: lap.surface,
+        track_temperature: lap.track_temperature,
+        sectors_ms,
+        created_at: lap.created_at,
+        game_version: lap.game_version.clone(),
+        bop_era: lap.bop_era.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{ConditionsFilter, Surface};
+    use crate::storage::testing::{drive, TempDatabase};
+
+    #[tokio::test]
+    async fn syncing_twice_copies_everything_once() -> Result<()> {
+        let from_file = TempDatabase::new("sync_from");
+        let to_file = TempDatabase::new("sync_to");
+        let from = SqliteStorage::connect(&from_file.url()).await?;
+        let to = SqliteStorage::connect(&to_file.url()).await?;
+        drive(&from, "Alice", "2024", 130000).await?;
+        drive(&from, "Alice", "2024", 129000).await?;
+        drive(&from, "Bob", "2024", 131000).await?;
+
+        let first = sync(&from, &to).await?;
+        assert_eq!((first.sessions, first.laps, first.best_laps), (3, 3, 2));
+        let second = sync(&from, &to).await?;
+        assert_eq!((second.sessions, second.laps, second.best_laps), (0, 0, 0));
+
+        let sessions = to.export_sessions().await?;
+        assert_eq!(sessions.len(), 3);
+        let mut laps = Vec::new();
+        for session in &sessions {
+            laps.extend(to.export_laps(session.id).await?);
+        }
+        let mut lap_times: Vec<_> = laps.iter().map(|lap| lap.lap_time_ms).collect();
+        lap_times.sort();
+        assert_eq!(lap_times, [129000, 130000, 131000]);
+
+        let track = to.find_track("spa", Some("2024")).await?.unwrap();
+        let conditions = ConditionsFilter {
+            surface: Surface::Dry,
+            track_temperature: None,
+            bop_era: None,
+        };
+        let leaderboard: Vec<_> = to
+            .leaderboard(track.id, None, None, &conditions)
+            .await?
+            .into_iter()
+            .map(|best_lap| (best_lap.driver_name, best_lap.lap_time_ms))
+            .collect();
+        assert_eq!(
+            leaderboard,
+            [("Alice".to_string(), 129000), ("Bob".to_string(), 131000)]
+        );
+        Ok(())
+    }
+}
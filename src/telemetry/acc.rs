@@ -0,0 +1,153 @@
+use crate::session::{Conditions, GripStatus, RainLevel, SessionType};
+use crate::telemetry::{SessionInfo, TelemetrySource, TelemetryState};
+use crate::validity::InvalidReason;
+use anyhow::Result;
+use simetry::assetto_corsa_competizione::{
+    Client, Penalty, RainIntensity, SessionType as AccSessionType, SimState, Status,
+    TrackGripStatus,
+};
+use std::time::{Duration, Instant};
+
+/// Reads from Assetto Corsa Competizione's shared memory.
+pub(crate) struct AccSource {
+    client: Option<Client>,
+    started: Instant,
+    cutting_penalty: bool,
+}
+
+impl AccSource {
+    pub(crate) fn new() -> AccSource {
+        AccSource {
+            client: None,
+            started: Instant::now(),
+            cutting_penalty: false,
+        }
+    }
+
+    fn normalise(&mut self, sim_state: &SimState) -> TelemetryState {
+        let graphics = &sim_state.graphics;
+        let cutting_penalty = is_cutting_penalty(graphics.penalty);
+        let penalty_given = cutting_penalty && !self.cutting_penalty;
+        self.cutting_penalty = cutting_penalty;
+
+        let invalid_reason = if !graphics.is_valid_lap {
+            Some(InvalidReason::TrackLimits)
+        } else if penalty_given {
+            Some(InvalidReason::Cutting)
+        } else if graphics.is_in_pit || graphics.is_in_pit_lane {
+            Some(InvalidReason::PitLane)
+        } else if sim_state.physics.is_ai_controlled {
+            Some(InvalidReason::AiControlled)
+        } else if graphics.status == Status::Replay {
+            Some(InvalidReason::Replay)
+        } else {
+            None
+        };
+
+        TelemetryState {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            session_index: graphics.session_index,
+            session_type: session_type(&graphics.session),
+            conditions: Conditions {
+                air_temperature: sim_state.physics.air_temperature,
+                track_temperature: sim_state.physics.road_temperature,
+                rain_level: rain_level(&graphics.rain_intensity),
+                grip_status: grip_status(&graphics.track_grip_status),
+            },
+            completed_laps: graphics.completed_laps,
+            last_lap_ms: lap_time(graphics.lap_timing.last.millis),
+            best_lap_ms: lap_time(graphics.lap_timing.best.millis),
+            current_sector_index: graphics.current_sector_index,
+            last_sector_ms: graphics.lap_timing.last_sector_ms,
+            invalid_reason,
+        }
+    }
+}
+
+fn lap_time(millis: i32) -> Option<i64> {
+    if millis < i32::MAX {
+        Some(millis as i64)
+    } else {
+        None
+    }
+}
+
+fn session_type(session: &AccSessionType) -> SessionType {
+    match session {
+        AccSessionType::Practice => SessionType::Practice,
+        AccSessionType::Qualify => SessionType::Qualifying,
+        AccSessionType::Race => SessionType::Race,
+        AccSessionType::Hotlap
+        | AccSessionType::HotlapSuperPole
+        | AccSessionType::HotStint
+        | AccSessionType::TimeAttack => SessionType::Hotlap,
+        AccSessionType::Unknown | AccSessionType::Drift | AccSessionType::Drag => {
+            SessionType::Unknown
+        }
+    }
+}
+
+fn rain_level(rain: &RainIntensity) -> RainLevel {
+    match rain {
+        RainIntensity::NoRain => RainLevel::NoRain,
+        RainIntensity::Drizzle => RainLevel::Drizzle,
+        RainIntensity::LightRain => RainLevel::LightRain,
+        RainIntensity::MediumRain => RainLevel::MediumRain,
+        RainIntensity::HeavyRain => RainLevel::HeavyRain,
+        RainIntensity::Thunderstorm => RainLevel::Thunderstorm,
+    }
+}
+
+fn grip_status(grip: &TrackGripStatus) -> GripStatus {
+    match grip {
+        TrackGripStatus::Green => GripStatus::Green,
+        TrackGripStatus::Fast => GripStatus::Fast,
+        TrackGripStatus::Optimum => GripStatus::Optimum,
+        TrackGripStatus::Greasy => GripStatus::Greasy,
+        TrackGripStatus::Damp => GripStatus::Damp,
+        TrackGripStatus::Wet => GripStatus::Wet,
+        TrackGripStatus::Flooded => GripStatus::Flooded,
+    }
+}
+
+fn is_cutting_penalty(penalty: Penalty) -> bool {
+    matches!(
+        penalty,
+        Penalty::DriveThroughCutting
+            | Penalty::StopAndGo10Cutting
+            | Penalty::StopAndGo20Cutting
+            | Penalty::StopAndGo30Cutting
+            | Penalty::DisqualifiedCutting
+            | Penalty::RemoveBestLaptimeCutting
+    )
+}
+
+#[async_trait::async_trait]
+impl TelemetrySource for AccSource {
+    async fn next_session(&mut self) -> Result<Option<SessionInfo>> {
+        let client = Client::connect(Duration::from_secs(1)).await;
+        let session = SessionInfo {
+            track: client.static_data().track.clone(),
+            car: client.static_data().car_model.clone(),
+            sector_count: client.static_data().sector_count,
+            game_version: client.static_data().ac_version.clone(),
+        };
+        self.client = Some(client);
+        self.started = Instant::now();
+        self.cutting_penalty = false;
+        Ok(Some(session))
+    }
+
+    async fn next_state(&mut self) -> Result<Option<TelemetryState>> {
+        let Some(client) = self.client.as_mut() else {
+            return Ok(None);
+        };
+        match client.next_sim_state().await {
+            Some(sim_state) => Ok(Some(self.normalise(&sim_state))),
+            None => {
+                self.client = None;
+                Ok(None)
+            }
+        }
+    }
+}
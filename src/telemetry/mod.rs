@@ -0,0 +1,81 @@
+mod r#acc;
+mod r#replay;
+
+use crate::session::{Conditions, SessionType};
+use crate::validity::InvalidReason;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub(crate) use r#acc::AccSource;
+pub(crate) use r#replay::{Recorder, Replay};
+
+/// Static details of the session the sim is running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SessionInfo {
+    /// The sim's own identifier for the track, e.g. `spa`.
+    pub(crate) track: String,
+    /// The sim's own identifier for the car, e.g. `bmw_m4_gt3`.
+    pub(crate) car: String,
+    pub(crate) sector_count: i32,
+    /// Empty in recordings made before the game's version was recorded.
+    #[serde(default)]
+    pub(crate) game_version: String,
+}
+
+/// A single reading from the sim, normalised so lap detection doesn't depend on which sim it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TelemetryState {
+    /// Time since the session started.
+    pub(crate) elapsed_ms: u64,
+    pub(crate) session_index: i32,
+    pub(crate) session_type: SessionType,
+    pub(crate) conditions: Conditions,
+    pub(crate) completed_laps: i32,
+    pub(crate) last_lap_ms: Option<i64>,
+    pub(crate) best_lap_ms: Option<i64>,
+    pub(crate) current_sector_index: i32,
+    pub(crate) last_sector_ms: i32,
+    /// Set while something is happening that invalidates the lap in progress.
+    pub(crate) invalid_reason: Option<InvalidReason>,
+}
+
+/// Somewhere sim states can be read from.
+#[async_trait::async_trait]
+pub(crate) trait TelemetrySource {
+    /// Waits for the next session to start. Returns `None` when there are no more sessions.
+    async fn next_session(&mut self) -> Result<Option<SessionInfo>>;
+
+    /// Returns the next state of the current session, or `None` once the session has ended.
+    async fn next_state(&mut self) -> Result<Option<TelemetryState>>;
+}
+
+/// Wraps another source, writing everything read from it to a recording.
+pub(crate) struct Recording<S> {
+    source: S,
+    recorder: Recorder,
+}
+
+impl<S: TelemetrySource> Recording<S> {
+    pub(crate) fn new(source: S, recorder: Recorder) -> Recording<S> {
+        Recording { source, recorder }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: TelemetrySource + Send> TelemetrySource for Recording<S> {
+    async fn next_session(&mut self) -> Result<Option<SessionInfo>> {
+        let session = self.source.next_session().await?;
+        if let Some(session) = &session {
+            self.recorder.write_session(session)?;
+        }
+        Ok(session)
+    }
+
+    async fn next_state(&mut self) -> Result<Option<TelemetryState>> {
+        let state = self.source.next_state().await?;
+        if let Some(state) = &state {
+            self.recorder.write_state(state)?;
+        }
+        Ok(state)
+    }
+}
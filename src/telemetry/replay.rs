@@ -0,0 +1,107 @@
+use crate::telemetry::{SessionInfo, TelemetrySource, TelemetryState};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// One line of a telemetry recording. A recording holds any number of sessions, each followed by
+/// its states.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RecordedLine {
+    Session(SessionInfo),
+    State(TelemetryState),
+}
+
+/// Writes sessions and states to a JSON lines file as they are read.
+pub(crate) struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub(crate) fn create(path: &Path) -> Result<Recorder> {
+        let file = File::create(path)
+            .with_context(|| format!("Unable to create recording {}", path.display()))?;
+        Ok(Recorder {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub(crate) fn write_session(&mut self, session: &SessionInfo) -> Result<()> {
+        self.write(&RecordedLine::Session(session.clone()))
+    }
+
+    pub(crate) fn write_state(&mut self, state: &TelemetryState) -> Result<()> {
+        self.write(&RecordedLine::State(state.clone()))
+    }
+
+    fn write(&mut self, line: &RecordedLine) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, line)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Plays back a recording made by [`Recorder`].
+pub(crate) struct Replay {
+    lines: VecDeque<RecordedLine>,
+    speed: f64,
+    last_elapsed_ms: u64,
+}
+
+impl Replay {
+    /// A speed of 0 replays states as fast as they can be processed.
+    pub(crate) fn open(path: &Path, speed: f64) -> Result<Replay> {
+        let file = File::open(path)
+            .with_context(|| format!("Unable to open recording {}", path.display()))?;
+        let mut lines = VecDeque::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            lines.push_back(
+                serde_json::from_str(&line).with_context(|| {
+                    format!("Invalid line {} in {}", number + 1, path.display())
+                })?,
+            );
+        }
+        Ok(Replay {
+            lines,
+            speed,
+            last_elapsed_ms: 0,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl TelemetrySource for Replay {
+    async fn next_session(&mut self) -> Result<Option<SessionInfo>> {
+        while let Some(line) = self.lines.pop_front() {
+            if let RecordedLine::Session(session) = line {
+                self.last_elapsed_ms = 0;
+                return Ok(Some(session));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn next_state(&mut self) -> Result<Option<TelemetryState>> {
+        if !matches!(self.lines.front(), Some(RecordedLine::State(_))) {
+            return Ok(None);
+        }
+        let Some(RecordedLine::State(state)) = self.lines.pop_front() else {
+            return Ok(None);
+        };
+        if self.speed > 0.0 {
+            let wait = state.elapsed_ms.saturating_sub(self.last_elapsed_ms);
+            tokio::time::sleep(Duration::from_secs_f64(wait as f64 / 1000.0 / self.speed)).await;
+        }
+        self.last_elapsed_ms = state.elapsed_ms;
+        Ok(Some(state))
+    }
+}
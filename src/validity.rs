@@ -0,0 +1,108 @@
+use crate::telemetry::TelemetryState;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Why a lap can't count as a personal best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum InvalidReason {
+    TrackLimits,
+    Cutting,
+    PitLane,
+    AiControlled,
+    Replay,
+}
+
+impl fmt::Display for InvalidReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            InvalidReason::TrackLimits => "track limits",
+            InvalidReason::Cutting => "cutting penalty",
+            InvalidReason::PitLane => "pit lane",
+            InvalidReason::AiControlled => "AI controlled",
+            InvalidReason::Replay => "replay",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Tracks whether the lap in progress is still valid. The first reason a lap was invalidated is kept.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LapValidity {
+    reason: Option<InvalidReason>,
+}
+
+impl LapValidity {
+    pub(crate) fn update(&mut self, state: &TelemetryState) {
+        if self.reason.is_none() {
+            self.reason = state.invalid_reason;
+        }
+    }
+
+    /// Closes the current lap, returning its validity and starting a fresh one.
+    pub(crate) fn finish_lap(&mut self) -> LapValidity {
+        LapValidity {
+            reason: self.reason.take(),
+        }
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        self.reason.is_none()
+    }
+
+    pub(crate) fn reason(&self) -> Option<InvalidReason> {
+        self.reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{Conditions, GripStatus, RainLevel, SessionType};
+
+    fn state(invalid_reason: Option<InvalidReason>) -> TelemetryState {
+        TelemetryState {
+            elapsed_ms: 0,
+            session_index: 0,
+            session_type: SessionType::Practice,
+            conditions: Conditions {
+                air_temperature: 21.5,
+                track_temperature: 28.0,
+                rain_level: RainLevel::NoRain,
+                grip_status: GripStatus::Optimum,
+            },
+            completed_laps: 0,
+            last_lap_ms: None,
+            best_lap_ms: None,
+            current_sector_index: 0,
+            last_sector_ms: 0,
+            invalid_reason,
+        }
+    }
+
+    #[test]
+    fn the_first_reason_a_lap_was_invalidated_is_kept() {
+        let mut validity = LapValidity::default();
+        validity.update(&state(None));
+        validity.update(&state(Some(InvalidReason::TrackLimits)));
+        validity.update(&state(Some(InvalidReason::Cutting)));
+        validity.update(&state(None));
+
+        let lap = validity.finish_lap();
+        assert!(!lap.is_valid());
+        assert_eq!(lap.reason(), Some(InvalidReason::TrackLimits));
+        assert!(validity.is_valid());
+    }
+
+    #[test]
+    fn an_out_lap_from_the_pit_lane_is_invalid() {
+        let mut validity = LapValidity::default();
+        validity.update(&state(Some(InvalidReason::PitLane)));
+        validity.update(&state(Some(InvalidReason::PitLane)));
+        validity.update(&state(None));
+        assert_eq!(validity.finish_lap().reason(), Some(InvalidReason::PitLane));
+
+        validity.update(&state(None));
+        assert!(validity.finish_lap().is_valid());
+    }
+}